@@ -0,0 +1,49 @@
+use crate::{api::Effect, bgr_color::BGRColor, SupportedDevice};
+
+/// An effect for every enabled device, expanded into the matching [`Effect`]
+/// of each device when it is created with
+/// [`Chroma::create_all_devices_effect`](crate::Chroma::create_all_devices_effect).
+///
+/// Devices are enabled when they are listed in
+/// [`InitRequest::device_supported`](crate::InitRequest::device_supported)
+/// and haven't been found to be missing.
+pub struct AllDevicesEffect {
+    generator: Box<dyn Fn(SupportedDevice) -> Effect + Send + Sync>,
+}
+
+impl AllDevicesEffect {
+    /// Lights every device with `color`.
+    #[must_use]
+    pub fn new_static(color: BGRColor) -> Self {
+        Self::from_fn(move |device| Effect::new_static(device, color))
+    }
+
+    /// Turns off the lighting of every device.
+    #[must_use]
+    pub fn new_none() -> Self {
+        Self::from_fn(Effect::new_none)
+    }
+
+    /// Creates the effect of each device with `generator`. Effects returned
+    /// for a different device than the one asked for are ignored.
+    #[must_use]
+    pub fn from_fn(generator: impl Fn(SupportedDevice) -> Effect + Send + Sync + 'static) -> Self {
+        Self {
+            generator: Box::new(generator),
+        }
+    }
+
+    /// The effect for `device`, or `None` if the generator gave an effect for
+    /// another device.
+    #[must_use]
+    pub fn effect_for(&self, device: SupportedDevice) -> Option<Effect> {
+        let effect = (self.generator)(device);
+        (effect.device() == device).then_some(effect)
+    }
+}
+
+impl From<BGRColor> for AllDevicesEffect {
+    fn from(color: BGRColor) -> Self {
+        Self::new_static(color)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{bgr_color::BGRColor, key_color::KeyColor};
+use crate::{bgr_color::BGRColor, key_color::KeyColor, SupportedDevice};
 use bevy::prelude::Component;
 use serde::{Deserialize, Serialize};
 
@@ -123,27 +123,263 @@ pub enum ChromaLinkEffect {
 }
 
 impl Effect {
+    #[must_use]
+    pub fn device(&self) -> SupportedDevice {
+        match self {
+            Effect::Keyboard(_) => SupportedDevice::Keyboard,
+            Effect::Mouse(_) => SupportedDevice::Mouse,
+            Effect::Mousepad(_) => SupportedDevice::Mousepad,
+            Effect::Headset(_) => SupportedDevice::Headset,
+            Effect::Keypad(_) => SupportedDevice::Keypad,
+            Effect::ChromaLink(_) => SupportedDevice::ChromaLink,
+        }
+    }
+
+    /// Returns a copy of this effect with `f` applied to every color in it.
+    /// Key colors keep their enable bit.
+    #[must_use]
+    pub fn map_colors(&self, f: impl Fn(BGRColor) -> BGRColor) -> Self {
+        match self {
+            Effect::Keyboard(effect) => Effect::Keyboard(effect.map_colors(f)),
+            Effect::Mouse(effect) => Effect::Mouse(effect.map_colors(f)),
+            Effect::Mousepad(effect) => Effect::Mousepad(effect.map_colors(f)),
+            Effect::Headset(effect) => Effect::Headset(effect.map_colors(f)),
+            Effect::Keypad(effect) => Effect::Keypad(effect.map_colors(f)),
+            Effect::ChromaLink(effect) => Effect::ChromaLink(effect.map_colors(f)),
+        }
+    }
+
+    /// Creates an effect that lights all of `device` with one color.
+    #[must_use]
+    pub fn new_static(device: SupportedDevice, color: BGRColor) -> Self {
+        match device {
+            SupportedDevice::Keyboard => Effect::Keyboard(KeyboardEffect::Static { color }),
+            SupportedDevice::Mouse => Effect::Mouse(MouseEffect::Static { color }),
+            SupportedDevice::Mousepad => Effect::Mousepad(MousepadEffect::Static { color }),
+            SupportedDevice::Headset => Effect::Headset(HeadsetEffect::Static { color }),
+            SupportedDevice::Keypad => Effect::Keypad(KeypadEffect::Static { color }),
+            SupportedDevice::ChromaLink => Effect::ChromaLink(ChromaLinkEffect::Static { color }),
+        }
+    }
+
+    /// Creates an effect that turns off all of `device`'s lighting.
+    #[must_use]
+    pub fn new_none(device: SupportedDevice) -> Self {
+        match device {
+            SupportedDevice::Keyboard => Effect::Keyboard(KeyboardEffect::None),
+            SupportedDevice::Mouse => Effect::Mouse(MouseEffect::None),
+            SupportedDevice::Mousepad => Effect::Mousepad(MousepadEffect::None),
+            SupportedDevice::Headset => Effect::Headset(HeadsetEffect::None),
+            SupportedDevice::Keypad => Effect::Keypad(KeypadEffect::None),
+            SupportedDevice::ChromaLink => Effect::ChromaLink(ChromaLinkEffect::None),
+        }
+    }
+
     #[must_use]
     pub(crate) fn get_api(&self) -> &'static str {
+        self.device().get_api()
+    }
+}
+
+impl KeyboardEffect {
+    #[must_use]
+    pub fn map_colors(&self, f: impl Fn(BGRColor) -> BGRColor) -> Self {
+        match self {
+            Self::None => Self::None,
+            Self::Static { color } => Self::Static { color: f(*color) },
+            Self::Custom(color) => Self::Custom(map_grid(color, &f)),
+            Self::Custom2 { color, key } => Self::Custom2 {
+                color: map_grid(color, &f),
+                key: map_key_grid(key, &f),
+            },
+            Self::CustomKey { color, key } => Self::CustomKey {
+                color: map_grid(color, &f),
+                key: map_key_grid(key, &f),
+            },
+        }
+    }
+}
+
+impl MouseEffect {
+    #[must_use]
+    pub fn map_colors(&self, f: impl Fn(BGRColor) -> BGRColor) -> Self {
+        match self {
+            Self::None => Self::None,
+            Self::Static { color } => Self::Static { color: f(*color) },
+            Self::Custom(color) => Self::Custom(map_grid(color, &f)),
+        }
+    }
+}
+
+impl MousepadEffect {
+    #[must_use]
+    pub fn map_colors(&self, f: impl Fn(BGRColor) -> BGRColor) -> Self {
+        match self {
+            Self::None => Self::None,
+            Self::Static { color } => Self::Static { color: f(*color) },
+            Self::Custom(color) => Self::Custom(color.map(&f)),
+        }
+    }
+}
+
+impl HeadsetEffect {
+    #[must_use]
+    pub fn map_colors(&self, f: impl Fn(BGRColor) -> BGRColor) -> Self {
+        match self {
+            Self::None => Self::None,
+            Self::Static { color } => Self::Static { color: f(*color) },
+            Self::Custom(color) => Self::Custom(color.map(&f)),
+        }
+    }
+}
+
+impl KeypadEffect {
+    #[must_use]
+    pub fn map_colors(&self, f: impl Fn(BGRColor) -> BGRColor) -> Self {
+        match self {
+            Self::None => Self::None,
+            Self::Static { color } => Self::Static { color: f(*color) },
+            Self::Custom(color) => Self::Custom(map_grid(color, &f)),
+        }
+    }
+}
+
+impl ChromaLinkEffect {
+    #[must_use]
+    pub fn map_colors(&self, f: impl Fn(BGRColor) -> BGRColor) -> Self {
         match self {
-            Effect::Keyboard(_) => "keyboard",
-            Effect::Mouse(_) => "mouse",
-            Effect::Mousepad(_) => "mousepad",
-            Effect::Headset(_) => "headset",
-            Effect::Keypad(_) => "keypad",
-            Effect::ChromaLink(_) => "chromalink",
+            Self::None => Self::None,
+            Self::Static { color } => Self::Static { color: f(*color) },
+            Self::Custom(color) => Self::Custom(color.map(&f)),
         }
     }
 }
 
+#[must_use]
+fn map_grid<const W: usize, const H: usize>(
+    grid: &[[BGRColor; W]; H],
+    f: &impl Fn(BGRColor) -> BGRColor,
+) -> [[BGRColor; W]; H] {
+    grid.map(|row| row.map(f))
+}
+
+#[must_use]
+fn map_key_grid<const W: usize, const H: usize>(
+    grid: &[[KeyColor; W]; H],
+    f: &impl Fn(BGRColor) -> BGRColor,
+) -> [[KeyColor; W]; H] {
+    grid.map(|row| row.map(|key| key.map_color(f)))
+}
+
+// https://assets.razerzone.com/dev_portal/REST/html/_rz_errors_8h.html
+/// A result code returned by the Chroma server.
+#[derive(Debug, Deserialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(from = "i64")]
+pub enum RzResult {
+    Success,
+    Invalid,
+    AccessDenied,
+    InvalidHandle,
+    NotSupported,
+    InvalidParameter,
+    ServiceNotActive,
+    SingleInstanceApp,
+    DeviceNotConnected,
+    NotFound,
+    RequestAborted,
+    AlreadyInitialized,
+    ResourceDisabled,
+    DeviceNotAvailable,
+    NotValidState,
+    NoMoreItems,
+    Failed,
+    /// A code that isn't listed in the Chroma documentation
+    Unknown(i32),
+}
+
+impl RzResult {
+    #[must_use]
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            0 => RzResult::Success,
+            -1 => RzResult::Invalid,
+            5 => RzResult::AccessDenied,
+            6 => RzResult::InvalidHandle,
+            50 => RzResult::NotSupported,
+            87 => RzResult::InvalidParameter,
+            1062 => RzResult::ServiceNotActive,
+            1152 => RzResult::SingleInstanceApp,
+            1167 => RzResult::DeviceNotConnected,
+            1168 => RzResult::NotFound,
+            1235 => RzResult::RequestAborted,
+            1247 => RzResult::AlreadyInitialized,
+            4309 => RzResult::ResourceDisabled,
+            4319 => RzResult::DeviceNotAvailable,
+            5023 => RzResult::NotValidState,
+            259 => RzResult::NoMoreItems,
+            // 0x80004005
+            -2147467259 => RzResult::Failed,
+            code => RzResult::Unknown(code),
+        }
+    }
+
+    #[must_use]
+    pub fn code(&self) -> i32 {
+        match self {
+            RzResult::Success => 0,
+            RzResult::Invalid => -1,
+            RzResult::AccessDenied => 5,
+            RzResult::InvalidHandle => 6,
+            RzResult::NotSupported => 50,
+            RzResult::InvalidParameter => 87,
+            RzResult::ServiceNotActive => 1062,
+            RzResult::SingleInstanceApp => 1152,
+            RzResult::DeviceNotConnected => 1167,
+            RzResult::NotFound => 1168,
+            RzResult::RequestAborted => 1235,
+            RzResult::AlreadyInitialized => 1247,
+            RzResult::ResourceDisabled => 4309,
+            RzResult::DeviceNotAvailable => 4319,
+            RzResult::NotValidState => 5023,
+            RzResult::NoMoreItems => 259,
+            RzResult::Failed => -2147467259,
+            RzResult::Unknown(code) => *code,
+        }
+    }
+
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        *self == RzResult::Success
+    }
+}
+
+impl From<i64> for RzResult {
+    /// Codes are 32-bit, but some are sent as unsigned numbers, such as
+    /// `RZRESULT_FAILED` being sent as `2147500037`
+    fn from(code: i64) -> Self {
+        Self::from_code(code as i32)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ResultResponse {
+    pub(crate) result: RzResult,
+}
+
 #[derive(Debug, Deserialize)]
 pub(crate) struct CreateEffectResponse {
-    #[serde(rename(deserialize = "result"))]
-    _result: i32, // TODO enum
+    result: RzResult,
+    /// Missing when the effect couldn't be created
+    #[serde(default)]
     id: String,
 }
 
 impl CreateEffectResponse {
+    #[must_use]
+    pub(crate) fn result(&self) -> RzResult {
+        self.result
+    }
+
     #[must_use]
     pub(crate) fn id(&self) -> &str {
         &self.id
@@ -157,3 +393,278 @@ pub(crate) struct SessionInfo {
     #[serde(rename(deserialize = "uri"))]
     pub(crate) root_url: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Color;
+    use proptest::prelude::*;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::bgr_color::{AlphaMode, ColorConversion, ColorSpace, OutOfRange};
+
+    fn color() -> impl Strategy<Value = BGRColor> {
+        any::<[u8; 3]>().prop_map(|[r, g, b]| BGRColor::from_rgb(r, g, b))
+    }
+
+    fn key() -> impl Strategy<Value = KeyColor> {
+        proptest::option::of(color()).prop_map(KeyColor::from)
+    }
+
+    /// Generates a `W` x `H` grid from a flat `Vec`, since nesting array
+    /// strategies for grids this size overflows the stack.
+    fn grid<T: Copy + Default + std::fmt::Debug, const W: usize, const H: usize>(
+        cell: impl Strategy<Value = T>,
+    ) -> impl Strategy<Value = [[T; W]; H]> {
+        proptest::collection::vec(cell, W * H).prop_map(|cells| {
+            let mut grid = [[T::default(); W]; H];
+            for (index, cell) in cells.into_iter().enumerate() {
+                grid[index / W][index % W] = cell;
+            }
+            grid
+        })
+    }
+
+    fn row<T: Copy + Default + std::fmt::Debug, const W: usize>(
+        cell: impl Strategy<Value = T>,
+    ) -> impl Strategy<Value = [T; W]> {
+        grid::<T, W, 1>(cell).prop_map(|[row]| row)
+    }
+
+    fn keyboard_effect() -> impl Strategy<Value = KeyboardEffect> {
+        prop_oneof![
+            Just(KeyboardEffect::None),
+            color().prop_map(|color| KeyboardEffect::Static { color }),
+            grid::<_, 22, 6>(color()).prop_map(KeyboardEffect::Custom),
+            (grid::<_, 24, 8>(color()), grid::<_, 22, 6>(key()),)
+                .prop_map(|(color, key)| KeyboardEffect::Custom2 { color, key }),
+            (grid::<_, 22, 6>(color()), grid::<_, 22, 6>(key()),)
+                .prop_map(|(color, key)| KeyboardEffect::CustomKey { color, key }),
+        ]
+    }
+
+    fn mouse_effect() -> impl Strategy<Value = MouseEffect> {
+        prop_oneof![
+            Just(MouseEffect::None),
+            color().prop_map(|color| MouseEffect::Static { color }),
+            grid::<_, 7, 9>(color()).prop_map(MouseEffect::Custom),
+        ]
+    }
+
+    fn mousepad_effect() -> impl Strategy<Value = MousepadEffect> {
+        prop_oneof![
+            Just(MousepadEffect::None),
+            color().prop_map(|color| MousepadEffect::Static { color }),
+            row::<_, 15>(color()).prop_map(MousepadEffect::Custom),
+        ]
+    }
+
+    fn headset_effect() -> impl Strategy<Value = HeadsetEffect> {
+        prop_oneof![
+            Just(HeadsetEffect::None),
+            color().prop_map(|color| HeadsetEffect::Static { color }),
+            row::<_, 5>(color()).prop_map(HeadsetEffect::Custom),
+        ]
+    }
+
+    fn keypad_effect() -> impl Strategy<Value = KeypadEffect> {
+        prop_oneof![
+            Just(KeypadEffect::None),
+            color().prop_map(|color| KeypadEffect::Static { color }),
+            grid::<_, 5, 4>(color()).prop_map(KeypadEffect::Custom),
+        ]
+    }
+
+    fn chroma_link_effect() -> impl Strategy<Value = ChromaLinkEffect> {
+        prop_oneof![
+            Just(ChromaLinkEffect::None),
+            color().prop_map(|color| ChromaLinkEffect::Static { color }),
+            row::<_, 5>(color()).prop_map(ChromaLinkEffect::Custom),
+        ]
+    }
+
+    fn effect() -> impl Strategy<Value = Effect> {
+        prop_oneof![
+            keyboard_effect().prop_map(Effect::Keyboard),
+            mouse_effect().prop_map(Effect::Mouse),
+            mousepad_effect().prop_map(Effect::Mousepad),
+            headset_effect().prop_map(Effect::Headset),
+            keypad_effect().prop_map(Effect::Keypad),
+            chroma_link_effect().prop_map(Effect::ChromaLink),
+        ]
+    }
+
+    /// Checks that `value` is a `rows` x `columns` grid (or a flat list when
+    /// `rows` is `None`) of colors, with key colors allowed when `keys` is
+    /// set.
+    fn assert_grid(value: &Value, rows: Option<usize>, columns: usize, keys: bool) {
+        let max = if keys { 0x01ff_ffff } else { 0x00ff_ffff };
+        let check_row = |row: &Value| {
+            let row = row.as_array().unwrap();
+            assert_eq!(row.len(), columns);
+            for color in row {
+                assert!(color.as_u64().unwrap() <= max, "{color} out of range");
+            }
+        };
+
+        match rows {
+            Some(rows) => {
+                let grid = value.as_array().unwrap();
+                assert_eq!(grid.len(), rows);
+                grid.iter().for_each(check_row);
+            }
+            None => check_row(value),
+        }
+    }
+
+    fn assert_wire_format(effect: &Effect) {
+        let json = serde_json::to_value(effect).unwrap();
+        let name = json["effect"].as_str().unwrap();
+        let param = &json["param"];
+
+        match (effect, name) {
+            (_, "CHROMA_NONE") => assert!(param.is_null()),
+            (_, "CHROMA_STATIC") => {
+                assert!(param["color"].as_u64().unwrap() <= 0x00ff_ffff);
+            }
+            (Effect::Keyboard(_), "CHROMA_CUSTOM") => assert_grid(param, Some(6), 22, false),
+            (Effect::Keyboard(_), "CHROMA_CUSTOM2") => {
+                assert_grid(&param["color"], Some(8), 24, false);
+                assert_grid(&param["key"], Some(6), 22, true);
+            }
+            (Effect::Keyboard(_), "CHROMA_CUSTOM_KEY") => {
+                assert_grid(&param["color"], Some(6), 22, false);
+                assert_grid(&param["key"], Some(6), 22, true);
+            }
+            (Effect::Mouse(_), "CHROMA_CUSTOM2") => assert_grid(param, Some(9), 7, false),
+            (Effect::Mousepad(_), "CHROMA_CUSTOM") => assert_grid(param, None, 15, false),
+            (Effect::Headset(_), "CHROMA_CUSTOM") => assert_grid(param, None, 5, false),
+            (Effect::Keypad(_), "CHROMA_CUSTOM") => assert_grid(param, Some(4), 5, false),
+            (Effect::ChromaLink(_), "CHROMA_CUSTOM") => assert_grid(param, None, 5, false),
+            (effect, name) => panic!("unexpected effect name {name} for {effect:?}"),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn effects_serialize_to_wire_format(effect in effect()) {
+            assert_wire_format(&effect);
+
+            // `Effect` is untagged, so the device is only known from the API
+            // it is sent to and round trips go through the device effects
+            let json = serde_json::to_string(&effect).unwrap();
+            let round_tripped = match effect {
+                Effect::Keyboard(_) => Effect::Keyboard(serde_json::from_str(&json).unwrap()),
+                Effect::Mouse(_) => Effect::Mouse(serde_json::from_str(&json).unwrap()),
+                Effect::Mousepad(_) => Effect::Mousepad(serde_json::from_str(&json).unwrap()),
+                Effect::Headset(_) => Effect::Headset(serde_json::from_str(&json).unwrap()),
+                Effect::Keypad(_) => Effect::Keypad(serde_json::from_str(&json).unwrap()),
+                Effect::ChromaLink(_) => {
+                    Effect::ChromaLink(serde_json::from_str(&json).unwrap())
+                }
+            };
+            prop_assert_eq!(round_tripped, effect);
+        }
+
+        #[test]
+        fn converted_colors_serialize_to_wire_format(
+            [r, g, b, a] in any::<[f32; 4]>(),
+            linear in any::<bool>(),
+            space in prop_oneof![Just(ColorSpace::Srgb), Just(ColorSpace::Linear)],
+            alpha in prop_oneof![Just(AlphaMode::Straight), Just(AlphaMode::Premultiplied)],
+            out_of_range in prop_oneof![Just(OutOfRange::Clamp), Just(OutOfRange::Normalize)],
+        ) {
+            let bevy_color = if linear {
+                Color::rgba_linear(r, g, b, a)
+            } else {
+                Color::rgba(r, g, b, a)
+            };
+            let conversion = ColorConversion {
+                space,
+                alpha,
+                out_of_range,
+            };
+            let color = BGRColor::from_color(bevy_color, conversion);
+            let key = KeyColor::from_color(bevy_color, conversion);
+            prop_assert_eq!(key.color(), color);
+
+            for effect in [
+                Effect::Keyboard(KeyboardEffect::CustomKey {
+                    color: [[color; 22]; 6],
+                    key: [[key; 22]; 6],
+                }),
+                Effect::Mouse(MouseEffect::Static { color }),
+                Effect::Mousepad(MousepadEffect::Custom([color; 15])),
+                Effect::Headset(HeadsetEffect::Custom([color; 5])),
+                Effect::Keypad(KeypadEffect::Custom([[color; 5]; 4])),
+                Effect::ChromaLink(ChromaLinkEffect::Static { color }),
+            ] {
+                assert_wire_format(&effect);
+            }
+        }
+    }
+
+    #[test]
+    fn result_codes_round_trip() {
+        for code in [0, -1, 87, 1167, 4319, 12345] {
+            assert_eq!(RzResult::from_code(code).code(), code);
+        }
+
+        let failed: RzResult = serde_json::from_str("2147500037").unwrap();
+        assert_eq!(failed, RzResult::Failed);
+        assert!(!failed.is_success());
+        assert!(RzResult::from_code(0).is_success());
+    }
+
+    #[test]
+    fn color_conversion_rounds_and_clamps() {
+        let conversion = ColorConversion::default();
+
+        assert_eq!(
+            BGRColor::from(Color::rgb(0.5, 0.0, 1.0)),
+            BGRColor::from_rgb(128, 0, 255)
+        );
+        assert_eq!(BGRColor::from(Color::rgb(2.0, -1.0, 0.0)), BGRColor::RED);
+        assert_eq!(
+            BGRColor::from(Color::rgb_linear(0.5, 0.5, 0.5)),
+            BGRColor::from_rgb(0xbc, 0xbc, 0xbc)
+        );
+        assert_eq!(
+            BGRColor::from_color(
+                Color::rgb_linear(0.5, 0.5, 0.5),
+                conversion.with_space(ColorSpace::Linear)
+            ),
+            BGRColor::from_rgb(128, 128, 128)
+        );
+        assert_eq!(
+            BGRColor::from_color(
+                Color::rgba_linear(1.0, 1.0, 1.0, 0.5),
+                conversion.with_space(ColorSpace::Linear)
+            ),
+            BGRColor::from_rgb(128, 128, 128)
+        );
+        assert_eq!(
+            BGRColor::from_color(
+                Color::rgba(1.0, 1.0, 1.0, 0.5),
+                conversion.with_alpha(AlphaMode::Premultiplied)
+            ),
+            BGRColor::WHITE
+        );
+        assert_eq!(
+            BGRColor::from_color(
+                Color::rgb_linear(2.0, 1.0, -1.0),
+                conversion.with_space(ColorSpace::Linear)
+            ),
+            BGRColor::from_rgb(255, 255, 0)
+        );
+        assert_eq!(
+            BGRColor::from_color(
+                Color::rgb_linear(2.0, 1.0, -1.0),
+                conversion
+                    .with_space(ColorSpace::Linear)
+                    .with_out_of_range(OutOfRange::Normalize)
+            ),
+            BGRColor::from_rgb(255, 128, 0)
+        );
+    }
+}
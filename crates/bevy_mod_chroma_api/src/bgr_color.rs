@@ -1,4 +1,9 @@
-use std::fmt::{Debug, Formatter, Result};
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    ops::{Add, Sub},
+    str::FromStr,
+};
 
 use bevy::prelude::Color;
 use serde::{Deserialize, Serialize};
@@ -7,6 +12,130 @@ use serde::{Deserialize, Serialize};
 pub struct BGRColor(u32);
 
 impl BGRColor {
+    pub const BLACK: Self = Self::from_rgb(0x00, 0x00, 0x00);
+    pub const WHITE: Self = Self::from_rgb(0xff, 0xff, 0xff);
+    pub const RED: Self = Self::from_rgb(0xff, 0x00, 0x00);
+    pub const GREEN: Self = Self::from_rgb(0x00, 0xff, 0x00);
+    pub const BLUE: Self = Self::from_rgb(0x00, 0x00, 0xff);
+    pub const YELLOW: Self = Self::from_rgb(0xff, 0xff, 0x00);
+    pub const CYAN: Self = Self::from_rgb(0x00, 0xff, 0xff);
+    pub const MAGENTA: Self = Self::from_rgb(0xff, 0x00, 0xff);
+    pub const ORANGE: Self = Self::from_rgb(0xff, 0xa5, 0x00);
+    pub const PURPLE: Self = Self::from_rgb(0x80, 0x00, 0x80);
+
+    #[must_use]
+    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self(((b as u32) << 16) | ((g as u32) << 8) | r as u32)
+    }
+
+    /// Parses a color from a `#rrggbb` or `#rgb` hex string. The leading `#`
+    /// is optional.
+    pub fn from_hex(hex: &str) -> Result<Self, ParseBGRColorError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseBGRColorError::InvalidDigit);
+        }
+
+        // The digits are all ASCII, so each of the slices below is on a char
+        // boundary
+        let channel = |range: std::ops::Range<usize>| {
+            u8::from_str_radix(&digits[range], 16).map_err(|_| ParseBGRColorError::InvalidDigit)
+        };
+
+        match digits.len() {
+            6 => Ok(Self::from_rgb(
+                channel(0..2)?,
+                channel(2..4)?,
+                channel(4..6)?,
+            )),
+            3 => Ok(Self::from_rgb(
+                channel(0..1)? * 0x11,
+                channel(1..2)? * 0x11,
+                channel(2..3)? * 0x11,
+            )),
+            length => Err(ParseBGRColorError::InvalidLength(length)),
+        }
+    }
+
+    /// Creates a color from hue (in degrees), saturation and value (both in
+    /// the range `0.0..=1.0`).
+    #[must_use]
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let saturation = saturation.clamp(0.0, 1.0);
+        let value = value.clamp(0.0, 1.0);
+
+        let chroma = value * saturation;
+        let (r, g, b) = hue_to_rgb(hue, chroma);
+        let m = value - chroma;
+
+        Self::from_rgb_f32(r + m, g + m, b + m)
+    }
+
+    /// Returns the hue (in degrees), saturation and value (both in the range
+    /// `0.0..=1.0`) of this color.
+    #[must_use]
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let [r, g, b] = self.to_rgb_f32();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let chroma = max - min;
+
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+
+        (rgb_to_hue(r, g, b, max, chroma), saturation, max)
+    }
+
+    /// Creates a color from hue (in degrees), saturation and lightness (both
+    /// in the range `0.0..=1.0`).
+    #[must_use]
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let saturation = saturation.clamp(0.0, 1.0);
+        let lightness = lightness.clamp(0.0, 1.0);
+
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let (r, g, b) = hue_to_rgb(hue, chroma);
+        let m = lightness - chroma / 2.0;
+
+        Self::from_rgb_f32(r + m, g + m, b + m)
+    }
+
+    /// Converts a Bevy [`Color`], in any of its representations, using the
+    /// given conversion settings.
+    #[must_use]
+    pub fn from_color(color: Color, conversion: ColorConversion) -> Self {
+        let [r, g, b, a] = color.as_linear_rgba_f32();
+        let alpha = match conversion.alpha {
+            AlphaMode::Straight => a.clamp(0.0, 1.0),
+            AlphaMode::Premultiplied => 1.0,
+        };
+
+        // Bring values into range before encoding so that out of range
+        // values, such as HDR colors, don't overflow into neighbouring channels
+        let [r, g, b] = [r, g, b].map(|value| (value * alpha).max(0.0));
+        let [r, g, b] = match conversion.out_of_range {
+            OutOfRange::Clamp => [r, g, b].map(|value| value.min(1.0)),
+            OutOfRange::Normalize => {
+                let brightest = r.max(g).max(b);
+                if brightest > 1.0 {
+                    [r, g, b].map(|value| value / brightest)
+                } else {
+                    [r, g, b]
+                }
+            }
+        };
+
+        match conversion.space {
+            ColorSpace::Srgb => Self::from_rgb_in(r, g, b, ColorSpace::Linear),
+            ColorSpace::Linear => Self::from_rgb_f32(r, g, b),
+        }
+    }
+
+    #[must_use]
+    pub fn to_bevy_color(&self) -> Color {
+        Color::rgb_u8(self.r() as u8, self.g() as u8, self.b() as u8)
+    }
+
     #[must_use]
     pub fn as_u32(&self) -> u32 {
         self.0
@@ -26,20 +155,427 @@ impl BGRColor {
     pub fn r(&self) -> u32 {
         self.0 & 0x0000ff
     }
+
+    #[must_use]
+    fn from_rgb_f32(r: f32, g: f32, b: f32) -> Self {
+        let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        Self::from_rgb(to_u8(r), to_u8(g), to_u8(b))
+    }
+
+    #[must_use]
+    fn to_rgb_f32(self) -> [f32; 3] {
+        [
+            self.r() as f32 / 255.0,
+            self.g() as f32 / 255.0,
+            self.b() as f32 / 255.0,
+        ]
+    }
+}
+
+/// The color space used for blending colors, or for the channel values
+/// produced when converting from a Bevy [`Color`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum ColorSpace {
+    /// Gamma-encoded sRGB channel values.
+    #[default]
+    Srgb,
+    /// Linear light. Blending in linear light avoids the dark band that sRGB
+    /// blending produces between saturated colors.
+    Linear,
+}
+
+/// How the alpha of a Bevy [`Color`] is handled when converting it to a
+/// [`BGRColor`], since Chroma devices have no alpha channel.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum AlphaMode {
+    /// The color channels are not multiplied by alpha, so the color is
+    /// composited over black (in linear light) by multiplying it by alpha.
+    #[default]
+    Straight,
+    /// The color channels are already multiplied by alpha and are used as
+    /// they are.
+    Premultiplied,
+}
+
+/// How channel values above `1.0`, such as those of HDR colors, are brought
+/// into range when converting a Bevy [`Color`]. Negative values are always
+/// clamped to `0.0`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum OutOfRange {
+    /// Clamp each channel to `1.0` on its own, which shifts the hue of colors
+    /// with more than one channel out of range.
+    #[default]
+    Clamp,
+    /// Scale all channels down together so that the brightest is `1.0`,
+    /// keeping the hue.
+    Normalize,
+}
+
+/// Settings for converting a Bevy [`Color`] with [`BGRColor::from_color`].
+///
+/// Channel values are always rounded to the nearest integer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct ColorConversion {
+    /// The encoding of the channel values sent to the device.
+    pub space: ColorSpace,
+    pub alpha: AlphaMode,
+    pub out_of_range: OutOfRange,
+}
+
+impl ColorConversion {
+    #[must_use]
+    pub fn with_space(mut self, space: ColorSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    #[must_use]
+    pub fn with_alpha(mut self, alpha: AlphaMode) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    #[must_use]
+    pub fn with_out_of_range(mut self, out_of_range: OutOfRange) -> Self {
+        self.out_of_range = out_of_range;
+        self
+    }
+}
+
+impl BGRColor {
+    /// Interpolates between `self` (at `t = 0.0`) and `other` (at `t = 1.0`)
+    /// in sRGB space. `t` is clamped to `0.0..=1.0`.
+    #[must_use]
+    pub fn lerp(&self, other: Self, t: f32) -> Self {
+        self.lerp_in(other, t, ColorSpace::Srgb)
+    }
+
+    /// Interpolates between `self` and `other` in linear light.
+    #[must_use]
+    pub fn lerp_linear(&self, other: Self, t: f32) -> Self {
+        self.lerp_in(other, t, ColorSpace::Linear)
+    }
+
+    #[must_use]
+    pub fn lerp_in(&self, other: Self, t: f32, space: ColorSpace) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let [from, to] = [self, &other].map(|color| color.to_rgb_in(space));
+        let [r, g, b] = [0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * t);
+
+        Self::from_rgb_in(r, g, b, space)
+    }
+
+    /// Adds each channel, saturating at `0xff`.
+    #[must_use]
+    pub fn saturating_add(&self, other: Self) -> Self {
+        self.zip_channels(other, u8::saturating_add)
+    }
+
+    /// Subtracts each channel, saturating at `0x00`.
+    #[must_use]
+    pub fn saturating_sub(&self, other: Self) -> Self {
+        self.zip_channels(other, u8::saturating_sub)
+    }
+
+    /// Multiplies each channel by `factor`, clamping the result.
+    #[must_use]
+    pub fn scale(&self, factor: f32) -> Self {
+        let [r, g, b] = self.to_rgb_f32();
+
+        Self::from_rgb_f32(r * factor, g * factor, b * factor)
+    }
+
+    /// Multiplies each channel by the matching channel of `other`, as in a
+    /// multiply blend.
+    #[must_use]
+    pub fn multiply(&self, other: Self) -> Self {
+        self.zip_channels(other, |a, b| {
+            ((u16::from(a) * u16::from(b) + 0x7f) / 0xff) as u8
+        })
+    }
+
+    /// Takes the maximum of each channel, unlike [`Ord::max`] which compares
+    /// whole colors.
+    #[must_use]
+    pub fn max_channels(&self, other: Self) -> Self {
+        self.zip_channels(other, Ord::max)
+    }
+
+    /// Takes the minimum of each channel.
+    #[must_use]
+    pub fn min_channels(&self, other: Self) -> Self {
+        self.zip_channels(other, Ord::min)
+    }
+
+    #[must_use]
+    fn zip_channels(&self, other: Self, f: impl Fn(u8, u8) -> u8) -> Self {
+        Self::from_rgb(
+            f(self.r() as u8, other.r() as u8),
+            f(self.g() as u8, other.g() as u8),
+            f(self.b() as u8, other.b() as u8),
+        )
+    }
+
+    #[must_use]
+    pub(crate) fn to_rgb_in(self, space: ColorSpace) -> [f32; 3] {
+        let rgb = self.to_rgb_f32();
+
+        match space {
+            ColorSpace::Srgb => rgb,
+            ColorSpace::Linear => rgb.map(srgb_to_linear),
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn from_rgb_in(r: f32, g: f32, b: f32, space: ColorSpace) -> Self {
+        match space {
+            ColorSpace::Srgb => Self::from_rgb_f32(r, g, b),
+            ColorSpace::Linear => {
+                Self::from_rgb_f32(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b))
+            }
+        }
+    }
+}
+
+/// Saturating, see [`BGRColor::saturating_add`].
+impl Add for BGRColor {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.saturating_add(other)
+    }
+}
+
+/// Saturating, see [`BGRColor::saturating_sub`].
+impl Sub for BGRColor {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.saturating_sub(other)
+    }
+}
+
+#[must_use]
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[must_use]
+pub(crate) fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[must_use]
+fn hue_to_rgb(hue: f32, chroma: f32) -> (f32, f32, f32) {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+    match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    }
+}
+
+#[must_use]
+fn rgb_to_hue(r: f32, g: f32, b: f32, max: f32, chroma: f32) -> f32 {
+    if chroma == 0.0 {
+        return 0.0;
+    }
+
+    let hue = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+
+    hue * 60.0
 }
 
 impl Debug for BGRColor {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "BGRColor({:#06X})", self.0)
     }
 }
 
+/// Formats the color as a `#rrggbb` hex string.
+impl Display for BGRColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r(), self.g(), self.b())
+    }
+}
+
+impl FromStr for BGRColor {
+    type Err = ParseBGRColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+/// Converts with the default [`ColorConversion`], see
+/// [`BGRColor::from_color`].
 impl From<Color> for BGRColor {
     fn from(color: Color) -> Self {
-        let r = (color.a() * color.r() * 255.0) as u32;
-        let g = (color.a() * color.g() * 255.0) as u32;
-        let b = (color.a() * color.b() * 255.0) as u32;
+        Self::from_color(color, ColorConversion::default())
+    }
+}
+
+impl From<BGRColor> for Color {
+    fn from(color: BGRColor) -> Self {
+        color.to_bevy_color()
+    }
+}
+
+/// (De)serializes a [`BGRColor`] as a `#rrggbb` hex string instead of its
+/// `u32` wire form, for use in asset files with
+/// `#[serde(with = "bevy_mod_chroma_api::bgr_color::hex")]`.
+///
+/// Deserialization accepts anything [`BGRColor::from_hex`] does.
+pub mod hex {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use super::BGRColor;
+
+    pub fn serialize<S>(color: &BGRColor, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(color)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BGRColor, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex = String::deserialize(deserializer)?;
+
+        BGRColor::from_hex(&hex).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ParseBGRColorError {
+    InvalidLength(usize),
+    InvalidDigit,
+}
+
+impl Display for ParseBGRColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(length) => write!(
+                f,
+                "invalid hex color length {length}, expected 3 or 6 digits"
+            ),
+            Self::InvalidDigit => f.write_str("invalid hex digit in color"),
+        }
+    }
+}
+
+impl Error for ParseBGRColorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips() {
+        let color = BGRColor::from_hex("#ff8800").unwrap();
+
+        assert_eq!(color, BGRColor::from_rgb(0xff, 0x88, 0x00));
+        assert_eq!(color.as_u32(), 0x0088ff);
+        assert_eq!(color.to_string(), "#ff8800");
+        assert_eq!("f80".parse::<BGRColor>(), Ok(color));
+        assert_eq!(
+            BGRColor::from_hex("#ff88"),
+            Err(ParseBGRColorError::InvalidLength(4))
+        );
+        assert_eq!(
+            BGRColor::from_hex("#gg8800"),
+            Err(ParseBGRColorError::InvalidDigit)
+        );
+    }
+
+    #[test]
+    fn serializes_wire_form_unless_hex_is_opted_into() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Asset {
+            #[serde(with = "hex")]
+            color: BGRColor,
+        }
+
+        let color = BGRColor::from_rgb(0xff, 0x87, 0x00);
+
+        assert_eq!(serde_json::to_string(&color).unwrap(), "34815");
+        assert_eq!(serde_json::from_str::<BGRColor>("34815").unwrap(), color);
+        assert!(serde_json::from_str::<BGRColor>("\"#ff8700\"").is_err());
+
+        let asset = Asset { color };
+        let json = serde_json::to_string(&asset).unwrap();
+        assert_eq!(json, r##"{"color":"#ff8700"}"##);
+        assert_eq!(serde_json::from_str::<Asset>(&json).unwrap(), asset);
+    }
+
+    #[test]
+    fn hsv_round_trips() {
+        for color in [
+            BGRColor::RED,
+            BGRColor::ORANGE,
+            BGRColor::CYAN,
+            BGRColor::PURPLE,
+            BGRColor::from_rgb(12, 200, 77),
+        ] {
+            let (hue, saturation, value) = color.to_hsv();
+
+            assert_eq!(BGRColor::from_hsv(hue, saturation, value), color);
+        }
+
+        assert_eq!(BGRColor::from_hsl(120.0, 1.0, 0.5), BGRColor::GREEN);
+        assert_eq!(BGRColor::from_hsl(0.0, 0.0, 1.0), BGRColor::WHITE);
+    }
+
+    #[test]
+    fn lerp_endpoints_and_midpoint() {
+        let black = BGRColor::BLACK;
+        let white = BGRColor::WHITE;
+
+        assert_eq!(black.lerp(white, 0.0), black);
+        assert_eq!(black.lerp(white, 1.0), white);
+        assert_eq!(black.lerp(white, 2.0), white);
+        assert_eq!(black.lerp(white, 0.5), BGRColor::from_rgb(0x80, 0x80, 0x80));
+        // 50% linear light is considerably brighter once sRGB encoded
+        assert_eq!(
+            black.lerp_linear(white, 0.5),
+            BGRColor::from_rgb(0xbc, 0xbc, 0xbc)
+        );
+    }
+
+    #[test]
+    fn channel_arithmetic() {
+        let a = BGRColor::from_rgb(200, 100, 10);
+        let b = BGRColor::from_rgb(100, 50, 20);
 
-        Self((b << 16) | (g << 8) | r)
+        assert_eq!(a + b, BGRColor::from_rgb(255, 150, 30));
+        assert_eq!(a - b, BGRColor::from_rgb(100, 50, 0));
+        assert_eq!(a.scale(0.5), BGRColor::from_rgb(100, 50, 5));
+        assert_eq!(a.scale(2.0), BGRColor::from_rgb(255, 200, 20));
+        assert_eq!(a.multiply(BGRColor::WHITE), a);
+        assert_eq!(a.multiply(BGRColor::BLACK), BGRColor::BLACK);
+        assert_eq!(a.max_channels(b), BGRColor::from_rgb(200, 100, 20));
+        assert_eq!(a.min_channels(b), BGRColor::from_rgb(100, 50, 10));
     }
 }
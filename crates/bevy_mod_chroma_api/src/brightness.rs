@@ -0,0 +1,253 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::{
+        App, DetectChangesMut, Plugin, PostUpdate, Query, Res, ResMut, Resource, Time, Window,
+    },
+    utils::HashMap,
+};
+
+use crate::SupportedDevice;
+
+/// Effective brightness is rounded to this many steps, so that fading doesn't
+/// re-send every device's state on every frame.
+const BRIGHTNESS_STEPS: f32 = 64.0;
+
+pub(crate) struct BrightnessPlugin;
+
+impl Plugin for BrightnessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChromaBrightness>()
+            .init_resource::<EffectiveBrightness>()
+            .add_systems(PostUpdate, system_update_effective_brightness);
+    }
+}
+
+/// Master dimmer for everything sent to Chroma devices.
+///
+/// Brightness scales every color of every effect just before it is sent, and
+/// changing it re-sends the current state of each device.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct ChromaBrightness {
+    global: f32,
+    devices: HashMap<SupportedDevice, f32>,
+    paused: bool,
+    dim_on_pause: Option<DimOnPause>,
+}
+
+impl Default for ChromaBrightness {
+    fn default() -> Self {
+        Self {
+            global: 1.0,
+            devices: HashMap::default(),
+            paused: false,
+            dim_on_pause: None,
+        }
+    }
+}
+
+impl ChromaBrightness {
+    #[must_use]
+    pub fn with_dim_on_pause(mut self, dim_on_pause: DimOnPause) -> Self {
+        self.dim_on_pause = Some(dim_on_pause);
+        self
+    }
+
+    #[must_use]
+    pub fn global(&self) -> f32 {
+        self.global
+    }
+
+    /// Sets the brightness of all devices, from `0.0` (off) to `1.0` (full).
+    pub fn set_global(&mut self, brightness: f32) {
+        self.global = brightness.clamp(0.0, 1.0);
+    }
+
+    /// Returns the brightness of a single device, before the global
+    /// brightness is applied.
+    #[must_use]
+    pub fn device(&self, device: SupportedDevice) -> f32 {
+        self.devices.get(&device).copied().unwrap_or(1.0)
+    }
+
+    /// Sets the brightness of a single device. This is multiplied with the
+    /// global brightness.
+    pub fn set_device(&mut self, device: SupportedDevice, brightness: f32) {
+        self.devices.insert(device, brightness.clamp(0.0, 1.0));
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Marks the app as paused, which dims the devices if
+    /// [`DimOnPause`] is set. A paused [`Time`] counts as paused too.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    #[must_use]
+    pub fn dim_on_pause(&self) -> Option<&DimOnPause> {
+        self.dim_on_pause.as_ref()
+    }
+
+    pub fn set_dim_on_pause(&mut self, dim_on_pause: Option<DimOnPause>) {
+        self.dim_on_pause = dim_on_pause;
+    }
+}
+
+/// Fades the brightness down while the app is paused, and optionally while
+/// its windows are unfocused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DimOnPause {
+    /// Multiplier applied on top of the normal brightness once fully dimmed.
+    pub brightness: f32,
+    /// How long it takes to fade between full and dimmed brightness.
+    pub fade_duration: Duration,
+    /// Whether losing window focus dims the same as pausing.
+    pub when_unfocused: bool,
+}
+
+impl Default for DimOnPause {
+    fn default() -> Self {
+        Self {
+            brightness: 0.25,
+            fade_duration: Duration::from_secs(1),
+            when_unfocused: true,
+        }
+    }
+}
+
+/// The brightness that effects are currently sent with, after pausing and
+/// fading are taken into account.
+#[derive(Resource, Debug, PartialEq)]
+pub(crate) struct EffectiveBrightness {
+    dim: f32,
+    devices: HashMap<SupportedDevice, f32>,
+}
+
+impl Default for EffectiveBrightness {
+    fn default() -> Self {
+        Self {
+            dim: 1.0,
+            devices: HashMap::default(),
+        }
+    }
+}
+
+impl EffectiveBrightness {
+    /// Combines the global and per-device brightness with the current `dim`,
+    /// rounded to [`BRIGHTNESS_STEPS`].
+    #[must_use]
+    fn new(brightness: &ChromaBrightness, dim: f32) -> Self {
+        let devices = SupportedDevice::ALL
+            .into_iter()
+            .map(|device| {
+                let value = brightness.global * brightness.device(device) * dim;
+
+                (
+                    device,
+                    (value * BRIGHTNESS_STEPS).round() / BRIGHTNESS_STEPS,
+                )
+            })
+            .collect();
+
+        Self { dim, devices }
+    }
+
+    #[must_use]
+    pub(crate) fn get(&self, device: SupportedDevice) -> f32 {
+        self.devices.get(&device).copied().unwrap_or(1.0)
+    }
+}
+
+impl DimOnPause {
+    /// Moves `dim` towards the dimmed brightness while `paused`, or back
+    /// towards full brightness otherwise, by at most what the fade allows in
+    /// `delta_seconds`.
+    #[must_use]
+    fn fade(&self, dim: f32, paused: bool, delta_seconds: f32) -> f32 {
+        let target = if paused { self.brightness } else { 1.0 };
+
+        let fade_duration = self.fade_duration.as_secs_f32();
+        let max_step = if fade_duration > 0.0 {
+            (1.0 - self.brightness).abs() * delta_seconds / fade_duration
+        } else {
+            f32::INFINITY
+        };
+
+        dim + (target - dim).clamp(-max_step, max_step)
+    }
+}
+
+fn system_update_effective_brightness(
+    time: Res<Time>,
+    brightness: Res<ChromaBrightness>,
+    windows: Query<&Window>,
+    mut effective: ResMut<EffectiveBrightness>,
+) {
+    let dim = match brightness.dim_on_pause {
+        Some(dim_on_pause) => {
+            let unfocused = !windows.is_empty() && !windows.iter().any(|window| window.focused);
+            let paused =
+                brightness.paused || time.is_paused() || (dim_on_pause.when_unfocused && unfocused);
+
+            // Use the raw delta, as the fade needs to keep running while
+            // `Time` is paused
+            dim_on_pause.fade(effective.dim, paused, time.raw_delta_seconds())
+        }
+        None => 1.0,
+    };
+
+    effective.set_if_neq(EffectiveBrightness::new(&brightness, dim));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_global_and_device_brightness_in_steps() {
+        let mut brightness = ChromaBrightness::default();
+        brightness.set_global(0.5);
+        brightness.set_device(SupportedDevice::Mouse, 0.5);
+        brightness.set_device(SupportedDevice::Headset, 2.0);
+
+        let effective = EffectiveBrightness::new(&brightness, 1.0);
+        assert_eq!(effective.get(SupportedDevice::Keyboard), 0.5);
+        assert_eq!(effective.get(SupportedDevice::Mouse), 0.25);
+        assert_eq!(effective.get(SupportedDevice::Headset), 0.5);
+
+        // 0.5 * 0.3 = 0.15 is rounded to the nearest 64th
+        let effective = EffectiveBrightness::new(&brightness, 0.3);
+        assert_eq!(effective.get(SupportedDevice::Keyboard), 10.0 / 64.0);
+    }
+
+    #[test]
+    fn dim_fades_towards_target_over_the_fade_duration() {
+        let dim_on_pause = DimOnPause {
+            brightness: 0.2,
+            fade_duration: Duration::from_secs(2),
+            when_unfocused: false,
+        };
+
+        // 0.8 over 2 seconds is 0.4 per second
+        assert!((dim_on_pause.fade(1.0, true, 0.5) - 0.8).abs() < 1e-6);
+        assert_eq!(dim_on_pause.fade(0.3, true, 1.0), 0.2);
+        assert!((dim_on_pause.fade(0.2, false, 1.0) - 0.6).abs() < 1e-6);
+        assert_eq!(dim_on_pause.fade(0.9, false, 1.0), 1.0);
+        assert_eq!(dim_on_pause.fade(1.0, false, 1.0), 1.0);
+    }
+
+    #[test]
+    fn dim_without_fade_duration_jumps_to_target() {
+        let dim_on_pause = DimOnPause {
+            fade_duration: Duration::ZERO,
+            ..DimOnPause::default()
+        };
+
+        assert_eq!(dim_on_pause.fade(1.0, true, 0.0), 0.25);
+        assert_eq!(dim_on_pause.fade(0.25, false, 0.0), 1.0);
+    }
+}
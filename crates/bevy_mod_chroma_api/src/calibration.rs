@@ -0,0 +1,180 @@
+use bevy::{prelude::Resource, utils::HashMap};
+
+use crate::{api::Effect, bgr_color::BGRColor, SupportedDevice};
+
+/// Per-device color calibration, applied to every [`Effect`] just before it is
+/// sent to the Chroma server.
+///
+/// Devices without a calibration are sent colors unchanged. When a device's
+/// calibration changes, its last applied effect is sent again with the new
+/// calibration.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct ChromaCalibration {
+    devices: HashMap<SupportedDevice, DeviceCalibration>,
+}
+
+impl ChromaCalibration {
+    #[must_use]
+    pub fn with_device(mut self, device: SupportedDevice, calibration: DeviceCalibration) -> Self {
+        self.set(device, calibration);
+        self
+    }
+
+    pub fn set(&mut self, device: SupportedDevice, calibration: DeviceCalibration) {
+        self.devices.insert(device, calibration);
+    }
+
+    pub fn remove(&mut self, device: SupportedDevice) -> Option<DeviceCalibration> {
+        self.devices.remove(&device)
+    }
+
+    #[must_use]
+    pub fn get(&self, device: SupportedDevice) -> Option<&DeviceCalibration> {
+        self.devices.get(&device)
+    }
+
+    #[must_use]
+    pub fn calibrate_effect(&self, effect: &Effect) -> Effect {
+        match self.get(effect.device()) {
+            Some(calibration) => effect.map_colors(|color| calibration.calibrate(color)),
+            None => *effect,
+        }
+    }
+}
+
+/// Corrects for how a device's LEDs display the sRGB colors we send them.
+///
+/// Each channel is first raised to the power of `gamma`, then scaled by the
+/// matching channel of `white_point` and by `gains`, in that order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceCalibration {
+    /// LEDs respond roughly linearly, so a gamma of around `2.2` makes sRGB
+    /// input look closer to how it would on a monitor. `1.0` leaves colors as
+    /// they are.
+    pub gamma: f32,
+    /// The color sent in place of pure white, e.g. a slightly warmer white to
+    /// counter LEDs that lean blue.
+    pub white_point: BGRColor,
+    /// Multipliers for the red, green and blue channels.
+    pub gains: [f32; 3],
+}
+
+impl Default for DeviceCalibration {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            white_point: BGRColor::WHITE,
+            gains: [1.0; 3],
+        }
+    }
+}
+
+impl DeviceCalibration {
+    #[must_use]
+    pub fn with_gamma(mut self, gamma: f32) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    #[must_use]
+    pub fn with_white_point(mut self, white_point: BGRColor) -> Self {
+        self.white_point = white_point;
+        self
+    }
+
+    #[must_use]
+    pub fn with_gains(mut self, r: f32, g: f32, b: f32) -> Self {
+        self.gains = [r, g, b];
+        self
+    }
+
+    #[must_use]
+    pub fn calibrate(&self, color: BGRColor) -> BGRColor {
+        let channels = [color.r(), color.g(), color.b()];
+        let white = [
+            self.white_point.r(),
+            self.white_point.g(),
+            self.white_point.b(),
+        ];
+
+        let [r, g, b] = [0, 1, 2].map(|i| {
+            let value = (channels[i] as f32 / 255.0).powf(self.gamma);
+            let value = value * (white[i] as f32 / 255.0) * self.gains[i];
+
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        });
+
+        BGRColor::from_rgb(r, g, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_calibration_is_identity() {
+        let calibration = DeviceCalibration::default();
+
+        for color in [
+            BGRColor::BLACK,
+            BGRColor::WHITE,
+            BGRColor::ORANGE,
+            BGRColor::from_rgb(12, 200, 77),
+        ] {
+            assert_eq!(calibration.calibrate(color), color);
+        }
+    }
+
+    #[test]
+    fn applies_gamma_white_point_and_gains_per_channel() {
+        let color = BGRColor::from_rgb(200, 100, 50);
+
+        assert_eq!(
+            DeviceCalibration::default()
+                .with_gains(0.5, 1.0, 2.0)
+                .calibrate(color),
+            BGRColor::from_rgb(100, 100, 100)
+        );
+        assert_eq!(
+            DeviceCalibration::default()
+                .with_gamma(2.2)
+                .calibrate(BGRColor::from_rgb(128, 255, 0)),
+            BGRColor::from_rgb(56, 255, 0)
+        );
+        assert_eq!(
+            DeviceCalibration::default()
+                .with_white_point(BGRColor::from_rgb(255, 128, 0))
+                .calibrate(BGRColor::WHITE),
+            BGRColor::from_rgb(255, 128, 0)
+        );
+        // Gains that push a channel out of range saturate
+        assert_eq!(
+            DeviceCalibration::default()
+                .with_gains(4.0, 1.0, 1.0)
+                .calibrate(color),
+            BGRColor::from_rgb(255, 100, 50)
+        );
+    }
+
+    #[test]
+    fn calibrates_effects_for_their_own_device() {
+        let calibration = ChromaCalibration::default().with_device(
+            SupportedDevice::Keyboard,
+            DeviceCalibration::default().with_gains(0.5, 0.5, 0.5),
+        );
+
+        assert_eq!(
+            calibration.calibrate_effect(&Effect::new_static(
+                SupportedDevice::Keyboard,
+                BGRColor::WHITE
+            )),
+            Effect::new_static(SupportedDevice::Keyboard, BGRColor::from_rgb(128, 128, 128))
+        );
+        assert_eq!(
+            calibration
+                .calibrate_effect(&Effect::new_static(SupportedDevice::Mouse, BGRColor::WHITE)),
+            Effect::new_static(SupportedDevice::Mouse, BGRColor::WHITE)
+        );
+    }
+}
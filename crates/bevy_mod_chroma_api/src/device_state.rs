@@ -0,0 +1,53 @@
+use bevy::{prelude::Resource, utils::HashMap};
+
+use crate::{api::Effect, grid::ColorGrid, SupportedDevice};
+
+/// The last effect that was successfully applied to each device, and the
+/// colors it lights the device with.
+///
+/// This mirrors what the Chroma server is showing, so it can be used to draw
+/// devices in-game or to check the lit state in tests. Effects are kept as
+/// they were created, before brightness and calibration are applied.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ChromaDeviceState {
+    devices: HashMap<SupportedDevice, DeviceState>,
+}
+
+#[derive(Debug, Clone)]
+struct DeviceState {
+    effect: Effect,
+    grid: ColorGrid,
+}
+
+impl ChromaDeviceState {
+    /// The last effect applied to `device`, if any has been.
+    #[must_use]
+    pub fn effect(&self, device: SupportedDevice) -> Option<&Effect> {
+        self.devices.get(&device).map(|state| &state.effect)
+    }
+
+    /// The colors of every LED of `device`, if an effect has been applied to
+    /// it.
+    #[must_use]
+    pub fn grid(&self, device: SupportedDevice) -> Option<&ColorGrid> {
+        self.devices.get(&device).map(|state| &state.grid)
+    }
+
+    /// Iterates over the devices that have had an effect applied, and their
+    /// colors.
+    pub fn iter(&self) -> impl Iterator<Item = (SupportedDevice, &ColorGrid)> {
+        self.devices
+            .iter()
+            .map(|(device, state)| (*device, &state.grid))
+    }
+
+    pub(crate) fn record_applied(&mut self, effect: &Effect) {
+        self.devices.insert(
+            effect.device(),
+            DeviceState {
+                effect: *effect,
+                grid: effect.to_grid(),
+            },
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use bevy::{
+    log::*,
+    prelude::{
+        resource_exists, App, Commands, Component, Entity, IntoSystemConfigs, Local, Plugin, Query,
+        Res, ResMut, Resource,
+    },
+    utils::HashMap,
+};
+use bevy_mod_chroma_request_lib::{
+    ExecuteHttpRequests, HttpRequestOptions, HttpRequestSet, HttpRequests, TypedRequestHandle,
+};
+
+use crate::{
+    api::{Effect, ResultResponse, RzResult},
+    heartbeat::SessionActivity,
+    ChromaRunner, SupportedDevice,
+};
+
+pub(crate) struct DevicesPlugin;
+
+impl Plugin for DevicesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            ExecuteHttpRequests,
+            (
+                system_probe_devices.in_set(HttpRequestSet::BeforeExecuteRequests),
+                system_gather_device_probes.in_set(HttpRequestSet::AfterGatherResponses),
+            )
+                .run_if(resource_exists::<ChromaRunner>()),
+        );
+    }
+}
+
+/// Which of the devices in [`InitRequest::device_supported`](crate::InitRequest::device_supported)
+/// are actually present.
+///
+/// Once the session is opened, each device is probed by sending it a
+/// `CHROMA_NONE` effect. Effects for devices that turn out to be missing are
+/// then handled according to the [`MissingDevicePolicy`], rather than failing
+/// one by one.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct ChromaDevices {
+    statuses: HashMap<SupportedDevice, DeviceStatus>,
+    missing_device_policy: MissingDevicePolicy,
+    probed: bool,
+}
+
+/// What is known about whether a device is present.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DeviceStatus {
+    /// The device hasn't been probed, either because probing hasn't finished
+    /// or because it wasn't listed as supported
+    NotProbed,
+    Present,
+    /// The server responded to the probe with an error
+    Missing(RzResult),
+}
+
+/// What to do with effects for devices that are missing.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum MissingDevicePolicy {
+    /// Drop the effects without sending them.
+    #[default]
+    Drop,
+    /// Light the given device with the average color of the effects instead,
+    /// if it is listed in [`InitRequest::device_supported`](crate::InitRequest::device_supported)
+    /// and was found to be present. Otherwise the effects are dropped.
+    ///
+    /// Effects applied together, such as with
+    /// [`Chroma::apply_to_all`](crate::Chroma::apply_to_all), aren't redirected
+    /// to a device that they already light.
+    Redirect(SupportedDevice),
+}
+
+impl ChromaDevices {
+    #[must_use]
+    pub(crate) fn new(supported: &[SupportedDevice]) -> Self {
+        Self {
+            statuses: supported
+                .iter()
+                .map(|device| (*device, DeviceStatus::NotProbed))
+                .collect(),
+            missing_device_policy: MissingDevicePolicy::default(),
+            probed: false,
+        }
+    }
+
+    #[must_use]
+    pub fn with_missing_device_policy(mut self, policy: MissingDevicePolicy) -> Self {
+        self.missing_device_policy = policy;
+        self
+    }
+
+    #[must_use]
+    pub fn missing_device_policy(&self) -> MissingDevicePolicy {
+        self.missing_device_policy
+    }
+
+    pub fn set_missing_device_policy(&mut self, policy: MissingDevicePolicy) {
+        self.missing_device_policy = policy;
+    }
+
+    #[must_use]
+    pub fn status(&self, device: SupportedDevice) -> DeviceStatus {
+        self.statuses
+            .get(&device)
+            .copied()
+            .unwrap_or(DeviceStatus::NotProbed)
+    }
+
+    #[must_use]
+    pub fn is_present(&self, device: SupportedDevice) -> bool {
+        self.status(device) == DeviceStatus::Present
+    }
+
+    /// Whether `device` was probed and found to be missing. Effects are only
+    /// held back from missing devices, so devices that haven't been probed
+    /// are still sent effects.
+    #[must_use]
+    pub fn is_missing(&self, device: SupportedDevice) -> bool {
+        matches!(self.status(device), DeviceStatus::Missing(_))
+    }
+
+    /// Whether every probe has finished, successfully or not.
+    #[must_use]
+    pub fn is_probed(&self) -> bool {
+        self.probed
+    }
+
+    /// Iterates over the devices that were found to be present.
+    pub fn present(&self) -> impl Iterator<Item = SupportedDevice> + '_ {
+        self.statuses
+            .iter()
+            .filter(|(_, status)| **status == DeviceStatus::Present)
+            .map(|(device, _)| *device)
+    }
+
+    /// The supported devices that haven't been found to be missing, in a
+    /// stable order.
+    #[must_use]
+    pub fn enabled(&self) -> Vec<SupportedDevice> {
+        let mut enabled: Vec<_> = self
+            .statuses
+            .iter()
+            .filter(|(_, status)| !matches!(status, DeviceStatus::Missing(_)))
+            .map(|(device, _)| *device)
+            .collect();
+        enabled.sort();
+        enabled
+    }
+
+    /// Returns what to send in place of `effect` when its device is missing,
+    /// or `None` if it should be dropped.
+    ///
+    /// Effects are only redirected to a device that was probed and found to
+    /// be present, as the server would reject anything else.
+    #[must_use]
+    pub(crate) fn redirect(&self, effect: &Effect) -> Option<Effect> {
+        match self.missing_device_policy {
+            MissingDevicePolicy::Drop => None,
+            MissingDevicePolicy::Redirect(device) => self
+                .is_present(device)
+                .then(|| Effect::new_static(device, effect.to_grid().average())),
+        }
+    }
+}
+
+#[derive(Component)]
+struct InFlightDeviceProbe {
+    device: SupportedDevice,
+    request_handle: Option<TypedRequestHandle<ResultResponse>>,
+}
+
+fn system_probe_devices(
+    mut commands: Commands,
+    mut requests: HttpRequests,
+    mut started: Local<bool>,
+    runner: Res<ChromaRunner>,
+    mut devices: ResMut<ChromaDevices>,
+) {
+    if *started {
+        return;
+    }
+
+    *started = true;
+    devices.probed = devices.statuses.is_empty();
+
+    for device in devices.statuses.keys() {
+        let _span = info_span!("chroma_probe_device", device = device.get_api()).entered();
+        let request_handle = requests.request_typed_with_options(
+            requests
+                .client()
+                .put(runner.get_session_url(device.get_api()))
+                .json(&Effect::new_none(*device)),
+            HttpRequestOptions::default().idempotent(),
+        );
+
+        commands.spawn(InFlightDeviceProbe {
+            device: *device,
+            request_handle: Some(request_handle),
+        });
+    }
+}
+
+fn system_gather_device_probes(
+    mut commands: Commands,
+    mut requests: HttpRequests,
+    mut activity: ResMut<SessionActivity>,
+    mut devices: ResMut<ChromaDevices>,
+    mut in_flight_probes: Query<(Entity, &mut InFlightDeviceProbe)>,
+) {
+    if devices.probed {
+        return;
+    }
+
+    let mut remaining = 0;
+    for (entity, mut in_flight_probe) in in_flight_probes.iter_mut() {
+        let Some(result) = requests.poll_typed(&mut in_flight_probe.request_handle) else {
+            remaining += 1;
+            continue;
+        };
+
+        let device = in_flight_probe.device;
+        if result.is_ok() {
+            activity.record();
+        }
+
+        match result {
+            Ok(response) if response.result.is_success() => {
+                devices.statuses.insert(device, DeviceStatus::Present);
+            }
+            Ok(response) => {
+                warn!(
+                    "chroma device {} is missing: {:?}",
+                    device.get_api(),
+                    response.result
+                );
+                devices
+                    .statuses
+                    .insert(device, DeviceStatus::Missing(response.result));
+            }
+            Err(err) => {
+                warn!(
+                    "failed to probe chroma device {}: {}",
+                    device.get_api(),
+                    err
+                );
+            }
+        }
+
+        commands.entity(entity).despawn();
+    }
+
+    // Probes are spawned in the same frame, so once none are left they have
+    // all finished
+    if remaining == 0 && !in_flight_probes.is_empty() {
+        devices.probed = true;
+
+        if let MissingDevicePolicy::Redirect(target) = devices.missing_device_policy {
+            if !devices.is_present(target)
+                && devices
+                    .statuses
+                    .values()
+                    .any(|status| matches!(status, DeviceStatus::Missing(_)))
+            {
+                warn!(
+                    "effects for missing chroma devices will be dropped, as the redirect target {} isn't present",
+                    target.get_api()
+                );
+            }
+        }
+    }
+}
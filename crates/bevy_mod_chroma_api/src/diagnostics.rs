@@ -0,0 +1,151 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::{App, IntoSystemConfigs, Local, Plugin, Query, Res, Resource, Time, With},
+};
+use bevy_mod_chroma_request_lib::{ExecuteHttpRequests, HttpRequestSet, HttpRequestStats};
+
+use crate::plugin::CreatedEffect;
+
+/// Registers [`Diagnostic`]s for requests sent by the Chroma plugin and the
+/// effects they create, so that they show up in `LogDiagnosticsPlugin` and
+/// other diagnostics overlays.
+///
+/// Add this after [`ChromaPlugin`](crate::ChromaPlugin).
+#[derive(Default)]
+pub struct ChromaDiagnosticsPlugin;
+
+impl Plugin for ChromaDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(
+            Self::REQUESTS_PER_SECOND,
+            "chroma_requests_per_second",
+            20,
+        ))
+        .register_diagnostic(
+            Diagnostic::new(Self::IN_FLIGHT_REQUESTS, "chroma_in_flight_requests", 20)
+                .with_smoothing_factor(0.0),
+        )
+        .register_diagnostic(
+            Diagnostic::new(Self::ROUND_TRIP_TIME, "chroma_round_trip_time", 20).with_suffix("ms"),
+        )
+        .register_diagnostic(
+            Diagnostic::new(Self::CREATE_FAILURE_RATE, "chroma_create_failure_rate", 20)
+                .with_suffix("%"),
+        )
+        .register_diagnostic(
+            Diagnostic::new(Self::APPLY_FAILURE_RATE, "chroma_apply_failure_rate", 20)
+                .with_suffix("%"),
+        )
+        .register_diagnostic(
+            Diagnostic::new(Self::DROPPED_APPLIES, "chroma_dropped_applies", 1)
+                .with_smoothing_factor(0.0),
+        )
+        .register_diagnostic(
+            Diagnostic::new(Self::EFFECTS_ALIVE, "chroma_effects_alive", 1)
+                .with_smoothing_factor(0.0),
+        )
+        .add_systems(
+            ExecuteHttpRequests,
+            system_chroma_diagnostics.after(HttpRequestSet::AfterGatherResponses),
+        );
+    }
+}
+
+impl ChromaDiagnosticsPlugin {
+    /// Requests sent per second, including retries.
+    pub const REQUESTS_PER_SECOND: DiagnosticId =
+        DiagnosticId::from_u128(150840263484927245911428359637440378624);
+    /// Requests that have been sent but not yet answered.
+    pub const IN_FLIGHT_REQUESTS: DiagnosticId =
+        DiagnosticId::from_u128(208532794447208011436566618227442395137);
+    /// Average round-trip time of the requests answered each frame.
+    pub const ROUND_TRIP_TIME: DiagnosticId =
+        DiagnosticId::from_u128(62071405406478123553993405785683431426);
+    /// Percentage of create requests answered each frame that failed.
+    pub const CREATE_FAILURE_RATE: DiagnosticId =
+        DiagnosticId::from_u128(291862330779532066384010834958779092995);
+    /// Percentage of apply requests answered each frame that failed.
+    pub const APPLY_FAILURE_RATE: DiagnosticId =
+        DiagnosticId::from_u128(127533823413716582092306934441659531268);
+    /// Total applies dropped because their deadline passed before they were
+    /// sent or answered, or because any of their devices are missing and the
+    /// effect couldn't be redirected. Applies are always sent one by one,
+    /// never coalesced.
+    pub const DROPPED_APPLIES: DiagnosticId =
+        DiagnosticId::from_u128(33424918520497616236458563427011661829);
+    /// Effects that have been created on the server.
+    pub const EFFECTS_ALIVE: DiagnosticId =
+        DiagnosticId::from_u128(240951346474811380357768498012384520198);
+}
+
+fn system_chroma_diagnostics(
+    mut diagnostics: Diagnostics,
+    mut last_request_stats: Local<HttpRequestStats>,
+    mut last_effect_stats: Local<EffectStats>,
+    time: Res<Time>,
+    request_stats: Res<HttpRequestStats>,
+    effect_stats: Res<EffectStats>,
+    created_effects: Query<(), With<CreatedEffect>>,
+) {
+    let delta_seconds = time.raw_delta_seconds_f64();
+    if delta_seconds > 0.0 {
+        let sent = request_stats.sent() - last_request_stats.sent();
+        diagnostics.add_measurement(ChromaDiagnosticsPlugin::REQUESTS_PER_SECOND, || {
+            sent as f64 / delta_seconds
+        });
+    }
+
+    diagnostics.add_measurement(ChromaDiagnosticsPlugin::IN_FLIGHT_REQUESTS, || {
+        request_stats.in_flight() as f64
+    });
+
+    let completed = request_stats.completed() - last_request_stats.completed();
+    if completed > 0 {
+        let round_trip = request_stats.total_round_trip() - last_request_stats.total_round_trip();
+        diagnostics.add_measurement(ChromaDiagnosticsPlugin::ROUND_TRIP_TIME, || {
+            round_trip.as_secs_f64() * 1000.0 / completed as f64
+        });
+    }
+
+    if let Some(rate) = failure_rate(
+        effect_stats.creates_completed - last_effect_stats.creates_completed,
+        effect_stats.creates_failed - last_effect_stats.creates_failed,
+    ) {
+        diagnostics.add_measurement(ChromaDiagnosticsPlugin::CREATE_FAILURE_RATE, || rate);
+    }
+
+    if let Some(rate) = failure_rate(
+        effect_stats.applies_completed - last_effect_stats.applies_completed,
+        effect_stats.applies_failed - last_effect_stats.applies_failed,
+    ) {
+        diagnostics.add_measurement(ChromaDiagnosticsPlugin::APPLY_FAILURE_RATE, || rate);
+    }
+
+    diagnostics.add_measurement(ChromaDiagnosticsPlugin::DROPPED_APPLIES, || {
+        effect_stats.applies_dropped as f64
+    });
+    diagnostics.add_measurement(ChromaDiagnosticsPlugin::EFFECTS_ALIVE, || {
+        created_effects.iter().count() as f64
+    });
+
+    *last_request_stats = request_stats.clone();
+    *last_effect_stats = effect_stats.clone();
+}
+
+/// Failures as a percentage of completed requests, or `None` when none
+/// completed.
+#[must_use]
+fn failure_rate(completed: u64, failed: u64) -> Option<f64> {
+    (completed > 0).then(|| failed as f64 / completed as f64 * 100.0)
+}
+
+/// Running totals of effect requests, kept by the Chroma plugin for
+/// diagnostics.
+#[derive(Resource, Debug, Clone, Default)]
+pub(crate) struct EffectStats {
+    pub(crate) creates_completed: u64,
+    pub(crate) creates_failed: u64,
+    pub(crate) applies_completed: u64,
+    pub(crate) applies_failed: u64,
+    pub(crate) applies_dropped: u64,
+}
@@ -0,0 +1,206 @@
+use crate::{
+    bgr_color::{BGRColor, ColorSpace},
+    key_color::KeyColor,
+};
+
+/// A color gradient made of any number of stops, sampled with
+/// [`Gradient::sample`].
+///
+/// Stops are [`BGRColor`]s by default. A gradient of [`KeyColor`]s samples to
+/// key colors, taking the enable bit of the stop before the sampled position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient<C = BGRColor> {
+    stops: Vec<GradientStop<C>>,
+    space: ColorSpace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop<C = BGRColor> {
+    pub position: f32,
+    pub color: C,
+}
+
+/// A color that can be used as the stops of a [`Gradient`].
+pub trait GradientColor: Copy + Default {
+    #[must_use]
+    fn lerp_in(&self, other: Self, t: f32, space: ColorSpace) -> Self;
+}
+
+impl GradientColor for BGRColor {
+    fn lerp_in(&self, other: Self, t: f32, space: ColorSpace) -> Self {
+        BGRColor::lerp_in(self, other, t, space)
+    }
+}
+
+impl GradientColor for KeyColor {
+    fn lerp_in(&self, other: Self, t: f32, space: ColorSpace) -> Self {
+        KeyColor::lerp_in(self, other, t, space)
+    }
+}
+
+impl<C> Default for Gradient<C> {
+    fn default() -> Self {
+        Self {
+            stops: Vec::new(),
+            space: ColorSpace::default(),
+        }
+    }
+}
+
+impl<C: GradientColor> Gradient<C> {
+    /// Creates a gradient interpolated in sRGB space. Stops do not need to be
+    /// given in order.
+    #[must_use]
+    pub fn new(stops: impl IntoIterator<Item = (f32, C)>) -> Self {
+        let mut stops: Vec<_> = stops
+            .into_iter()
+            .map(|(position, color)| GradientStop { position, color })
+            .collect();
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+        Self {
+            stops,
+            space: ColorSpace::default(),
+        }
+    }
+
+    /// Creates a gradient with the given colors spread evenly over
+    /// `0.0..=1.0`.
+    #[must_use]
+    pub fn evenly_spaced(colors: impl IntoIterator<Item = C>) -> Self {
+        let colors: Vec<_> = colors.into_iter().collect();
+        let last_index = colors.len().saturating_sub(1).max(1) as f32;
+
+        Self::new(
+            colors
+                .into_iter()
+                .enumerate()
+                .map(|(index, color)| (index as f32 / last_index, color)),
+        )
+    }
+
+    #[must_use]
+    pub fn with_space(mut self, space: ColorSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    #[must_use]
+    pub fn stops(&self) -> &[GradientStop<C>] {
+        &self.stops
+    }
+
+    #[must_use]
+    pub fn space(&self) -> ColorSpace {
+        self.space
+    }
+
+    /// Samples the gradient at `position`. Positions before the first or after
+    /// the last stop take the color of that stop, as does a NaN position. An
+    /// empty gradient is black, and disabled for [`KeyColor`]s.
+    #[must_use]
+    pub fn sample(&self, position: f32) -> C {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return C::default(),
+        };
+
+        if position.is_nan() || self.stops.len() == 1 || position <= first.position {
+            return first.color;
+        }
+
+        if position >= last.position {
+            return last.color;
+        }
+
+        // The checks above guarantee that there are at least two stops, with
+        // one on either side of `position`, so this always finds a pair
+        let end_index = self
+            .stops
+            .iter()
+            .position(|stop| stop.position > position)
+            .unwrap_or(self.stops.len() - 1);
+        let start = self.stops[end_index - 1];
+        let end = self.stops[end_index];
+
+        let t = (position - start.position) / (end.position - start.position);
+
+        start.color.lerp_in(end.color, t, self.space)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_between_many_stops() {
+        let gradient = Gradient::new([
+            (1.0, BGRColor::BLUE),
+            (0.0, BGRColor::RED),
+            (0.5, BGRColor::GREEN),
+        ]);
+
+        assert_eq!(gradient.sample(-1.0), BGRColor::RED);
+        assert_eq!(gradient.sample(0.0), BGRColor::RED);
+        assert_eq!(gradient.sample(0.25), BGRColor::from_rgb(0x80, 0x80, 0x00));
+        assert_eq!(gradient.sample(0.5), BGRColor::GREEN);
+        assert_eq!(gradient.sample(0.75), BGRColor::from_rgb(0x00, 0x80, 0x80));
+        assert_eq!(gradient.sample(1.0), BGRColor::BLUE);
+        assert_eq!(gradient.sample(2.0), BGRColor::BLUE);
+    }
+
+    #[test]
+    fn samples_in_linear_light() {
+        let gradient = Gradient::evenly_spaced([BGRColor::RED, BGRColor::GREEN])
+            .with_space(ColorSpace::Linear);
+
+        assert_eq!(gradient.sample(0.5), BGRColor::from_rgb(0xbc, 0xbc, 0x00));
+    }
+
+    #[test]
+    fn degenerate_gradients() {
+        assert_eq!(Gradient::<BGRColor>::default().sample(0.5), BGRColor::BLACK);
+        assert_eq!(
+            Gradient::evenly_spaced([BGRColor::CYAN]).sample(0.5),
+            BGRColor::CYAN
+        );
+        // Coincident stops produce a hard edge rather than dividing by zero
+        let hard_edge = Gradient::new([
+            (0.0, BGRColor::RED),
+            (0.5, BGRColor::RED),
+            (0.5, BGRColor::BLUE),
+            (1.0, BGRColor::BLUE),
+        ]);
+        assert_eq!(hard_edge.sample(0.49), BGRColor::RED);
+        assert_eq!(hard_edge.sample(0.5), BGRColor::BLUE);
+    }
+
+    #[test]
+    fn nan_positions_take_the_first_stop() {
+        let single = Gradient::new([(0.5, BGRColor::CYAN)]);
+        assert_eq!(single.sample(f32::NAN), BGRColor::CYAN);
+        assert_eq!(single.sample(0.75), BGRColor::CYAN);
+
+        let gradient = Gradient::evenly_spaced([BGRColor::RED, BGRColor::BLUE]);
+        assert_eq!(gradient.sample(f32::NAN), BGRColor::RED);
+    }
+
+    #[test]
+    fn key_color_stops_keep_their_enable_bit() {
+        let enabled = KeyColor::from(BGRColor::RED);
+        let disabled = KeyColor::from(None::<BGRColor>);
+        let gradient = Gradient::new([(0.0, enabled), (0.5, disabled), (1.0, enabled)]);
+
+        let first_half = gradient.sample(0.25);
+        assert!(first_half.is_enabled());
+        assert_eq!(first_half.color(), BGRColor::from_rgb(0x80, 0x00, 0x00));
+
+        let second_half = gradient.sample(0.75);
+        assert!(!second_half.is_enabled());
+        assert_eq!(second_half.color(), BGRColor::from_rgb(0x80, 0x00, 0x00));
+
+        assert!(gradient.sample(1.0).is_enabled());
+        assert!(!Gradient::<KeyColor>::default().sample(0.5).is_enabled());
+    }
+}
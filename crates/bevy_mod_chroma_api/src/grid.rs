@@ -0,0 +1,222 @@
+use crate::{
+    api::{
+        ChromaLinkEffect, Effect, HeadsetEffect, KeyboardEffect, KeypadEffect, MouseEffect,
+        MousepadEffect,
+    },
+    bgr_color::BGRColor,
+    key_color::KeyColor,
+    SupportedDevice,
+};
+
+/// The colors shown by every LED of a device, row by row.
+///
+/// Created from an effect with [`Effect::to_grid`], which fills the grid for
+/// `Static` effects and leaves it black for `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ColorGrid {
+    rows: usize,
+    columns: usize,
+    colors: Vec<BGRColor>,
+}
+
+impl ColorGrid {
+    /// Creates a grid with every cell set to `color`.
+    #[must_use]
+    pub fn filled(rows: usize, columns: usize, color: BGRColor) -> Self {
+        Self {
+            rows,
+            columns,
+            colors: vec![color; rows * columns],
+        }
+    }
+
+    /// Creates a black grid the size of `device`'s LED layout.
+    #[must_use]
+    pub fn for_device(device: SupportedDevice) -> Self {
+        let (rows, columns) = device.grid_size();
+        Self::filled(rows, columns, BGRColor::BLACK)
+    }
+
+    #[must_use]
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    #[must_use]
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the color at `row` and `column`, or `None` if either is out of
+    /// bounds.
+    #[must_use]
+    pub fn get(&self, row: usize, column: usize) -> Option<BGRColor> {
+        (row < self.rows && column < self.columns).then(|| self.colors[row * self.columns + column])
+    }
+
+    /// Iterates over the rows of the grid, top to bottom.
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[BGRColor]> {
+        self.colors.chunks(self.columns.max(1))
+    }
+
+    /// The mean color of every cell, or black for an empty grid.
+    #[must_use]
+    pub fn average(&self) -> BGRColor {
+        if self.colors.is_empty() {
+            return BGRColor::BLACK;
+        }
+
+        let (r, g, b) = self.colors.iter().fold((0, 0, 0), |(r, g, b), color| {
+            (
+                r + color.r() as usize,
+                g + color.g() as usize,
+                b + color.b() as usize,
+            )
+        });
+        let count = self.colors.len();
+
+        BGRColor::from_rgb(
+            ((r + count / 2) / count) as u8,
+            ((g + count / 2) / count) as u8,
+            ((b + count / 2) / count) as u8,
+        )
+    }
+
+    fn set(&mut self, row: usize, column: usize, color: BGRColor) {
+        if row < self.rows && column < self.columns {
+            self.colors[row * self.columns + column] = color;
+        }
+    }
+
+    fn fill_from<const W: usize, const H: usize>(&mut self, grid: &[[BGRColor; W]; H]) {
+        for (row, colors) in grid.iter().enumerate() {
+            for (column, color) in colors.iter().enumerate() {
+                self.set(row, column, *color);
+            }
+        }
+    }
+
+    /// Overrides cells with the colors of enabled keys.
+    fn overlay_keys<const W: usize, const H: usize>(&mut self, keys: &[[KeyColor; W]; H]) {
+        for (row, keys) in keys.iter().enumerate() {
+            for (column, key) in keys.iter().enumerate() {
+                if key.is_enabled() {
+                    self.set(row, column, key.color());
+                }
+            }
+        }
+    }
+}
+
+impl SupportedDevice {
+    /// The number of rows and columns of LEDs on this device.
+    #[must_use]
+    pub fn grid_size(&self) -> (usize, usize) {
+        match self {
+            SupportedDevice::Keyboard => (6, 22),
+            SupportedDevice::Mouse => (9, 7),
+            SupportedDevice::Mousepad => (1, 15),
+            SupportedDevice::Headset => (1, 5),
+            SupportedDevice::Keypad => (4, 5),
+            SupportedDevice::ChromaLink => (1, 5),
+        }
+    }
+}
+
+impl Effect {
+    /// Expands the effect into the colors of every LED of its device.
+    ///
+    /// The extended grid of [`KeyboardEffect::Custom2`] is cropped to the
+    /// standard keyboard layout.
+    #[must_use]
+    pub fn to_grid(&self) -> ColorGrid {
+        let mut grid = ColorGrid::for_device(self.device());
+        let rows = grid.rows;
+        let columns = grid.columns;
+
+        match self {
+            Effect::Keyboard(effect) => match effect {
+                KeyboardEffect::None => {}
+                KeyboardEffect::Static { color } => grid = ColorGrid::filled(rows, columns, *color),
+                KeyboardEffect::Custom(color) => grid.fill_from(color),
+                KeyboardEffect::Custom2 { color, key } => {
+                    grid.fill_from(color);
+                    grid.overlay_keys(key);
+                }
+                KeyboardEffect::CustomKey { color, key } => {
+                    grid.fill_from(color);
+                    grid.overlay_keys(key);
+                }
+            },
+            Effect::Mouse(effect) => match effect {
+                MouseEffect::None => {}
+                MouseEffect::Static { color } => grid = ColorGrid::filled(rows, columns, *color),
+                MouseEffect::Custom(color) => grid.fill_from(color),
+            },
+            Effect::Mousepad(effect) => match effect {
+                MousepadEffect::None => {}
+                MousepadEffect::Static { color } => {
+                    grid = ColorGrid::filled(rows, columns, *color);
+                }
+                MousepadEffect::Custom(color) => grid.fill_from(&[*color]),
+            },
+            Effect::Headset(effect) => match effect {
+                HeadsetEffect::None => {}
+                HeadsetEffect::Static { color } => grid = ColorGrid::filled(rows, columns, *color),
+                HeadsetEffect::Custom(color) => grid.fill_from(&[*color]),
+            },
+            Effect::Keypad(effect) => match effect {
+                KeypadEffect::None => {}
+                KeypadEffect::Static { color } => grid = ColorGrid::filled(rows, columns, *color),
+                KeypadEffect::Custom(color) => grid.fill_from(color),
+            },
+            Effect::ChromaLink(effect) => match effect {
+                ChromaLinkEffect::None => {}
+                ChromaLinkEffect::Static { color } => {
+                    grid = ColorGrid::filled(rows, columns, *color);
+                }
+                ChromaLinkEffect::Custom(color) => grid.fill_from(&[*color]),
+            },
+        }
+
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_fills_and_none_is_black() {
+        let grid = Effect::Mouse(MouseEffect::Static {
+            color: BGRColor::RED,
+        })
+        .to_grid();
+        assert_eq!((grid.rows(), grid.columns()), (9, 7));
+        assert!(grid
+            .iter_rows()
+            .flatten()
+            .all(|color| *color == BGRColor::RED));
+
+        let grid = Effect::Keypad(KeypadEffect::None).to_grid();
+        assert!(grid
+            .iter_rows()
+            .flatten()
+            .all(|color| *color == BGRColor::BLACK));
+    }
+
+    #[test]
+    fn enabled_keys_override_colors() {
+        let mut color = [[BGRColor::BLUE; 22]; 6];
+        color[0][1] = BGRColor::GREEN;
+        let mut key = [[KeyColor::default(); 22]; 6];
+        key[0][0] = KeyColor::from(BGRColor::RED);
+
+        let grid = Effect::Keyboard(KeyboardEffect::CustomKey { color, key }).to_grid();
+        assert_eq!(grid.get(0, 0), Some(BGRColor::RED));
+        assert_eq!(grid.get(0, 1), Some(BGRColor::GREEN));
+        assert_eq!(grid.get(5, 21), Some(BGRColor::BLUE));
+        assert_eq!(grid.get(6, 0), None);
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::{Event, Resource},
+    utils::Instant,
+};
+
+/// How much each new latency sample moves the average latency.
+const LATENCY_SMOOTHING: f32 = 0.2;
+
+/// How the heartbeats that keep the Chroma session alive are faring.
+///
+/// The session is considered unhealthy once enough heartbeats in a row have
+/// failed, which is also announced with a [`ChromaHealthChanged`] event.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct ChromaHealth {
+    failure_threshold: u32,
+    latency: Option<Duration>,
+    average_latency: Option<Duration>,
+    consecutive_failures: u32,
+    tick: Option<u32>,
+    last_success: Option<Instant>,
+}
+
+impl Default for ChromaHealth {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            latency: None,
+            average_latency: None,
+            consecutive_failures: 0,
+            tick: None,
+            last_success: None,
+        }
+    }
+}
+
+impl ChromaHealth {
+    #[must_use]
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.set_failure_threshold(failure_threshold);
+        self
+    }
+
+    /// How many heartbeats in a row must fail before the session is
+    /// considered unhealthy. Defaults to 3.
+    #[must_use]
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    pub fn set_failure_threshold(&mut self, failure_threshold: u32) {
+        self.failure_threshold = failure_threshold.max(1);
+    }
+
+    /// Round-trip time of the last successful heartbeat.
+    #[must_use]
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// Round-trip time of recent successful heartbeats, smoothed so that a
+    /// single slow heartbeat doesn't stand out.
+    #[must_use]
+    pub fn average_latency(&self) -> Option<Duration> {
+        self.average_latency
+    }
+
+    #[must_use]
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// The tick reported by the server in the last successful heartbeat.
+    #[must_use]
+    pub fn tick(&self) -> Option<u32> {
+        self.tick
+    }
+
+    #[must_use]
+    pub fn last_success(&self) -> Option<Instant> {
+        self.last_success
+    }
+
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures < self.failure_threshold
+    }
+
+    pub(crate) fn record_success(&mut self, latency: Duration, tick: u32) {
+        self.latency = Some(latency);
+        self.average_latency = Some(match self.average_latency {
+            Some(average) => {
+                average.mul_f32(1.0 - LATENCY_SMOOTHING) + latency.mul_f32(LATENCY_SMOOTHING)
+            }
+            None => latency,
+        });
+        self.consecutive_failures = 0;
+        self.tick = Some(tick);
+        self.last_success = Some(Instant::now());
+    }
+
+    pub(crate) fn record_failure(&mut self) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+    }
+}
+
+/// Sent whenever [`ChromaHealth::is_healthy`] changes, so that the app can
+/// warn the user or start a new session.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChromaHealthChanged {
+    pub healthy: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn becomes_unhealthy_after_threshold_failures() {
+        let mut health = ChromaHealth::default().with_failure_threshold(2);
+        assert!(health.is_healthy());
+
+        health.record_failure();
+        assert!(health.is_healthy());
+        health.record_failure();
+        assert!(!health.is_healthy());
+
+        health.record_success(Duration::from_millis(10), 7);
+        assert!(health.is_healthy());
+        assert_eq!(health.consecutive_failures(), 0);
+        assert_eq!(health.tick(), Some(7));
+    }
+
+    #[test]
+    fn average_latency_is_smoothed() {
+        let mut health = ChromaHealth::default();
+
+        health.record_success(Duration::from_millis(10), 1);
+        assert_eq!(health.average_latency(), Some(Duration::from_millis(10)));
+
+        health.record_success(Duration::from_millis(60), 2);
+        assert_eq!(health.latency(), Some(Duration::from_millis(60)));
+
+        let average = health.average_latency().unwrap();
+        assert!(average > Duration::from_millis(10) && average < Duration::from_millis(60));
+    }
+}
@@ -1,37 +1,112 @@
 use std::time::Duration;
 
 use bevy::{
+    log::*,
     prelude::{
-        resource_exists, App, Commands, Component, Condition, Entity, IntoSystemConfigs, Plugin,
-        PostUpdate, Query, Res,
+        resource_exists, App, Commands, Component, DetectChanges, Entity, EventWriter,
+        IntoSystemConfigs, Local, Plugin, PostUpdate, Query, Res, ResMut, Resource,
     },
-    time::common_conditions::on_timer,
-    utils::Instant,
+    utils::{
+        tracing::{field, Instrument},
+        Instant,
+    },
+};
+use bevy_mod_chroma_request_lib::{
+    spawn_io_task, ExecuteHttpRequests, HttpRequestError, HttpRequestOptions, HttpRequestPriority,
+    HttpRequestSet, HttpRequestTransport, HttpRequests, TypedRequestHandle,
 };
-use bevy_mod_chroma_request_lib::{HttpRequestHandle, HttpRequests};
+use crossbeam_channel::{Receiver, Sender};
+use futures_timer::Delay;
+use futures_util::future::{self, AbortHandle, Abortable, Either};
+use reqwest::{Client, Url};
 use serde::{Deserialize, Serialize};
 
-use crate::ChromaRunner;
+use crate::{
+    health::{ChromaHealth, ChromaHealthChanged},
+    ChromaRunner,
+};
 
-static HEARTBEAT_INTERVAL: f32 = 1.0;
-static HEARTBEAT_TIMEOUT: f32 = 10.0;
 static HEARTBEAT_API: &str = "heartbeat";
 
 pub(crate) struct HeartbeatPlugin;
 
 impl Plugin for HeartbeatPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            PostUpdate,
-            (
-                system_heartbeat_keepalive.run_if(
-                    resource_exists::<ChromaRunner>()
-                        .and_then(on_timer(Duration::from_secs_f32(HEARTBEAT_INTERVAL))),
-                ),
-                system_heartbeat_cleanup
-                    .run_if(on_timer(Duration::from_secs_f32(HEARTBEAT_INTERVAL))),
-            ),
-        );
+        app.init_resource::<ChromaHeartbeatSettings>()
+            .init_resource::<SessionActivity>()
+            .init_resource::<ChromaHealth>()
+            .add_event::<ChromaHealthChanged>()
+            .add_systems(
+                PostUpdate,
+                (
+                    system_heartbeat_keepalive,
+                    system_background_heartbeat_start,
+                )
+                    .run_if(resource_exists::<ChromaRunner>()),
+            )
+            .add_systems(
+                ExecuteHttpRequests,
+                (
+                    system_heartbeat_gather,
+                    system_background_heartbeat_gather
+                        .run_if(resource_exists::<BackgroundHeartbeat>()),
+                )
+                    .in_set(HttpRequestSet::AfterGatherResponses),
+            );
+    }
+}
+
+/// How often heartbeats are sent to keep the Chroma session alive.
+///
+/// Set up front with [`ChromaPlugin::with_heartbeat`](crate::ChromaPlugin::with_heartbeat),
+/// or changed later through the resource.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChromaHeartbeatSettings {
+    /// Time between heartbeats. Defaults to 1 second.
+    pub interval: Duration,
+    /// How long a heartbeat can go unanswered before it counts as failed.
+    /// Defaults to 10 seconds.
+    pub timeout: Duration,
+    /// Skips heartbeats when another request to the session has succeeded
+    /// within the interval, since the server counts any request as a
+    /// keepalive. Has no effect on background heartbeats. Defaults to `false`.
+    ///
+    /// [`ChromaHealth`] only tracks heartbeats, so it isn't updated while
+    /// heartbeats are being skipped.
+    pub adaptive: bool,
+    /// Sends heartbeats from a task on the `IoTaskPool` instead of from a
+    /// system, so that they keep going while the app is paused, minimized or
+    /// stuck on a long frame. Results are still reported to [`ChromaHealth`].
+    /// Defaults to `false`.
+    ///
+    /// Background heartbeats are sent straight through the
+    /// [`HttpRequestTransport`], so they aren't retried or counted in
+    /// `HttpRequestStats`.
+    pub background: bool,
+}
+
+impl Default for ChromaHeartbeatSettings {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(10),
+            adaptive: false,
+            background: false,
+        }
+    }
+}
+
+/// When the session last answered a request other than a heartbeat, for
+/// adaptive heartbeats. Only successful responses count, as a request that
+/// never reached the server doesn't keep the session alive.
+#[derive(Resource, Default)]
+pub(crate) struct SessionActivity {
+    last_response: Option<Instant>,
+}
+
+impl SessionActivity {
+    pub(crate) fn record(&mut self) {
+        self.last_response = Some(Instant::now());
     }
 }
 
@@ -40,53 +115,259 @@ pub(crate) struct HeartbeatRequest;
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct HeartbeatResponse {
-    #[serde(rename(deserialize = "tick"))]
-    _tick: u32,
+    tick: u32,
 }
 
 #[derive(Component)]
 struct InFlightHeartbeatRequest {
     spawned_at: Instant,
-    request_handle: Option<HttpRequestHandle>,
+    request_handle: Option<TypedRequestHandle<HeartbeatResponse>>,
 }
 
 impl InFlightHeartbeatRequest {
     #[must_use]
-    fn is_expired(&self) -> bool {
-        Instant::now().duration_since(self.spawned_at) > Duration::from_secs_f32(HEARTBEAT_TIMEOUT)
+    fn is_expired(&self, timeout: Duration) -> bool {
+        Instant::now().duration_since(self.spawned_at) > timeout
     }
 }
 
 fn system_heartbeat_keepalive(
     mut commands: Commands,
     mut requests: HttpRequests,
+    mut last_heartbeat: Local<Option<Instant>>,
+    activity: Res<SessionActivity>,
     runner: Res<ChromaRunner>,
+    settings: Res<ChromaHeartbeatSettings>,
 ) {
-    let handle = requests.request(
+    if settings.background {
+        return;
+    }
+
+    let last_keepalive = if settings.adaptive {
+        last_heartbeat.max(activity.last_response)
+    } else {
+        *last_heartbeat
+    };
+
+    if last_keepalive.is_some_and(|last_keepalive| last_keepalive.elapsed() < settings.interval) {
+        return;
+    }
+
+    let _span = info_span!("chroma_heartbeat").entered();
+
+    // Heartbeats keep the session alive, so must not wait behind a queue of
+    // effect requests
+    let handle = requests.request_typed_with_options(
         requests
             .client()
             .put(runner.get_session_url(HEARTBEAT_API))
             .json(&HeartbeatRequest),
+        HttpRequestOptions::default()
+            .with_priority(HttpRequestPriority::High)
+            .idempotent(),
     );
 
+    *last_heartbeat = Some(Instant::now());
+
     commands.spawn(InFlightHeartbeatRequest {
         spawned_at: Instant::now(),
         request_handle: Some(handle),
     });
 }
 
-fn system_heartbeat_cleanup(
+fn system_heartbeat_gather(
     mut commands: Commands,
     mut requests: HttpRequests,
+    mut health: ResMut<ChromaHealth>,
+    settings: Res<ChromaHeartbeatSettings>,
+    mut health_changed: EventWriter<ChromaHealthChanged>,
     mut in_flight_requests: Query<(Entity, &mut InFlightHeartbeatRequest)>,
 ) {
+    let was_healthy = health.is_healthy();
+
     for (entity, mut in_flight_request) in in_flight_requests.iter_mut() {
-        if !in_flight_request.is_expired() {
+        if in_flight_request.is_expired(settings.timeout) {
+            let request_handle = in_flight_request.request_handle.take().unwrap();
+            requests.dispose_typed(request_handle);
+            commands.entity(entity).despawn();
+
+            warn!("heartbeat timed out");
+            health.record_failure();
             continue;
         }
 
-        let request_handle = in_flight_request.request_handle.take().unwrap();
-        requests.dispose(request_handle);
+        let Some(result) = requests.poll_typed(&mut in_flight_request.request_handle) else {
+            continue;
+        };
+
+        match result {
+            Ok(response) => {
+                health.record_success(in_flight_request.spawned_at.elapsed(), response.tick);
+            }
+            Err(err) => {
+                warn!("heartbeat failed: {}", err);
+                health.record_failure();
+            }
+        }
+
         commands.entity(entity).despawn();
     }
+
+    report_health_change(was_healthy, &health, &mut health_changed);
+}
+
+fn report_health_change(
+    was_healthy: bool,
+    health: &ChromaHealth,
+    health_changed: &mut EventWriter<ChromaHealthChanged>,
+) {
+    let is_healthy = health.is_healthy();
+    if is_healthy == was_healthy {
+        return;
+    }
+
+    if !is_healthy {
+        error!(
+            "chroma session is unhealthy after {} failed heartbeats",
+            health.consecutive_failures()
+        );
+    }
+
+    health_changed.send(ChromaHealthChanged {
+        healthy: is_healthy,
+    });
+}
+
+/// Receives the results of heartbeats sent by the background task. The task
+/// is aborted once this resource is removed or replaced.
+#[derive(Resource)]
+struct BackgroundHeartbeat {
+    receiver: Receiver<BackgroundHeartbeatResult>,
+    abort_handle: AbortHandle,
+}
+
+impl Drop for BackgroundHeartbeat {
+    /// Aborts the task straight away, rather than leaving it to send one more
+    /// heartbeat with the old settings before noticing that the receiver is
+    /// gone.
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
+}
+
+struct BackgroundHeartbeatResult {
+    latency: Duration,
+    result: Result<HeartbeatResponse, HttpRequestError>,
+}
+
+fn system_background_heartbeat_start(
+    mut commands: Commands,
+    requests: HttpRequests,
+    transport: Res<HttpRequestTransport>,
+    runner: Res<ChromaRunner>,
+    settings: Res<ChromaHeartbeatSettings>,
+    background: Option<Res<BackgroundHeartbeat>>,
+) {
+    if !settings.background {
+        if background.is_some() {
+            commands.remove_resource::<BackgroundHeartbeat>();
+        }
+
+        return;
+    }
+
+    // The task is started again with the new settings whenever they change
+    if background.is_some() && !settings.is_changed() && !runner.is_changed() {
+        return;
+    }
+
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    spawn_io_task(Abortable::new(
+        background_heartbeat(
+            requests.client().clone(),
+            transport.clone(),
+            runner.get_session_url(HEARTBEAT_API),
+            *settings,
+            sender,
+        ),
+        abort_registration,
+    ))
+    .detach();
+
+    // Replacing the resource aborts the task started with the old settings
+    commands.insert_resource(BackgroundHeartbeat {
+        receiver,
+        abort_handle,
+    });
+}
+
+/// Sends heartbeats straight through the transport, so they bypass the queue,
+/// retries and [`HttpRequestStats`](bevy_mod_chroma_request_lib::HttpRequestStats)
+/// of [`HttpRequests`].
+async fn background_heartbeat(
+    client: Client,
+    transport: HttpRequestTransport,
+    url: Url,
+    settings: ChromaHeartbeatSettings,
+    sender: Sender<BackgroundHeartbeatResult>,
+) {
+    loop {
+        let span = info_span!(
+            "chroma_heartbeat",
+            tick = field::Empty,
+            error = field::Empty
+        );
+        let spawned_at = Instant::now();
+        let result = match client.put(url.clone()).json(&HeartbeatRequest).build() {
+            Ok(request) => {
+                let response =
+                    future::select(transport.send(request), Delay::new(settings.timeout));
+                match response.instrument(span.clone()).await {
+                    Either::Left((result, _)) => {
+                        result.and_then(|response| Ok(response.json::<HeartbeatResponse>()?))
+                    }
+                    Either::Right(_) => Err(HttpRequestError::Timeout),
+                }
+            }
+            Err(err) => Err(err.into()),
+        };
+
+        match &result {
+            Ok(response) => span.record("tick", response.tick),
+            Err(err) => span.record("error", field::display(err)),
+        };
+
+        let result = BackgroundHeartbeatResult {
+            latency: spawned_at.elapsed(),
+            result,
+        };
+
+        // The receiver is dropped when the task is no longer wanted
+        if sender.send(result).is_err() {
+            return;
+        }
+
+        Delay::new(settings.interval.saturating_sub(spawned_at.elapsed())).await;
+    }
+}
+
+fn system_background_heartbeat_gather(
+    background: Res<BackgroundHeartbeat>,
+    mut health: ResMut<ChromaHealth>,
+    mut health_changed: EventWriter<ChromaHealthChanged>,
+) {
+    let was_healthy = health.is_healthy();
+
+    for BackgroundHeartbeatResult { latency, result } in background.receiver.try_iter() {
+        match result {
+            Ok(response) => health.record_success(latency, response.tick),
+            Err(err) => {
+                warn!("heartbeat failed: {}", err);
+                health.record_failure();
+            }
+        }
+    }
+
+    report_health_change(was_healthy, &health, &mut health_changed);
 }
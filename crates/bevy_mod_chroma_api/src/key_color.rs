@@ -1,9 +1,12 @@
-use std::fmt::{Debug, Formatter, Result};
+use std::{
+    fmt::{Debug, Formatter, Result},
+    ops::{Add, Sub},
+};
 
 use bevy::prelude::Color;
 use serde::{Deserialize, Serialize};
 
-use crate::bgr_color::BGRColor;
+use crate::bgr_color::{BGRColor, ColorConversion, ColorSpace};
 
 const KEY_COLOR_MASK: u32 = 0x0100_0000;
 
@@ -35,6 +38,92 @@ impl KeyColor {
     pub fn r(&self) -> u32 {
         self.0 & 0x0000ff
     }
+
+    /// Converts a Bevy [`Color`] into an enabled key color, see
+    /// [`BGRColor::from_color`].
+    #[must_use]
+    pub fn from_color(color: Color, conversion: ColorConversion) -> Self {
+        Self::from(BGRColor::from_color(color, conversion))
+    }
+
+    /// Returns the color of this key, without the enable bit.
+    #[must_use]
+    pub fn color(&self) -> BGRColor {
+        BGRColor::from_rgb(self.r() as u8, self.g() as u8, self.b() as u8)
+    }
+
+    /// Interpolates the color towards `other` in sRGB space, keeping the
+    /// enable bit of `self`. See [`BGRColor::lerp`].
+    #[must_use]
+    pub fn lerp(&self, other: Self, t: f32) -> Self {
+        self.map_color(|color| color.lerp(other.color(), t))
+    }
+
+    /// Interpolates the color towards `other` in linear light, keeping the
+    /// enable bit of `self`. See [`BGRColor::lerp_linear`].
+    #[must_use]
+    pub fn lerp_linear(&self, other: Self, t: f32) -> Self {
+        self.map_color(|color| color.lerp_linear(other.color(), t))
+    }
+
+    #[must_use]
+    pub fn lerp_in(&self, other: Self, t: f32, space: ColorSpace) -> Self {
+        self.map_color(|color| color.lerp_in(other.color(), t, space))
+    }
+
+    #[must_use]
+    pub fn saturating_add(&self, other: Self) -> Self {
+        self.map_color(|color| color.saturating_add(other.color()))
+    }
+
+    #[must_use]
+    pub fn saturating_sub(&self, other: Self) -> Self {
+        self.map_color(|color| color.saturating_sub(other.color()))
+    }
+
+    #[must_use]
+    pub fn scale(&self, factor: f32) -> Self {
+        self.map_color(|color| color.scale(factor))
+    }
+
+    #[must_use]
+    pub fn multiply(&self, other: Self) -> Self {
+        self.map_color(|color| color.multiply(other.color()))
+    }
+
+    #[must_use]
+    pub fn max_channels(&self, other: Self) -> Self {
+        self.map_color(|color| color.max_channels(other.color()))
+    }
+
+    #[must_use]
+    pub fn min_channels(&self, other: Self) -> Self {
+        self.map_color(|color| color.min_channels(other.color()))
+    }
+
+    /// Replaces the color of this key, keeping the enable bit.
+    #[must_use]
+    pub fn map_color(&self, f: impl FnOnce(BGRColor) -> BGRColor) -> Self {
+        Self((self.0 & KEY_COLOR_MASK) | f(self.color()).as_u32())
+    }
+}
+
+/// Saturating, keeping the enable bit of the left-hand side.
+impl Add for KeyColor {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.saturating_add(other)
+    }
+}
+
+/// Saturating, keeping the enable bit of the left-hand side.
+impl Sub for KeyColor {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.saturating_sub(other)
+    }
 }
 
 impl Debug for KeyColor {
@@ -76,3 +165,22 @@ impl From<Option<Color>> for KeyColor {
         Self::from(maybe_color.map(BGRColor::from))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_keeps_enable_bit() {
+        let enabled = KeyColor::from(BGRColor::from_rgb(200, 100, 0));
+        let disabled = KeyColor::from(None::<BGRColor>);
+
+        assert!(enabled.lerp(disabled, 1.0).is_enabled());
+        assert_eq!(enabled.lerp(disabled, 1.0).color(), BGRColor::BLACK);
+        assert!(!disabled.lerp(enabled, 1.0).is_enabled());
+        assert_eq!(disabled.lerp(enabled, 1.0).color(), enabled.color());
+        assert!((enabled + enabled).is_enabled());
+        assert_eq!((enabled + enabled).color(), BGRColor::from_rgb(255, 200, 0));
+        assert_eq!(enabled.scale(0.5).as_u32(), 0x0100_3264);
+    }
+}
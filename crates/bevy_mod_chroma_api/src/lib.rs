@@ -3,37 +3,101 @@ use std::time::Duration;
 use api::Effect;
 use bevy::{
     ecs::system::SystemParam,
-    prelude::{Commands, Entity, Resource},
+    prelude::{Commands, Entity, Res, Resource},
     utils::Instant,
 };
-use plugin::ApplyEffectRequest;
+use plugin::{ApplyEffectRequest, DeleteEffectRequest, EffectGroup};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 
+pub mod all_devices;
 pub mod api;
 pub mod bgr_color;
+pub mod brightness;
+pub mod calibration;
+pub mod device_state;
+pub mod devices;
+pub mod diagnostics;
+pub mod gradient;
+pub mod grid;
+pub mod health;
+pub mod heartbeat;
 pub mod key_color;
+pub mod preview;
+pub mod replay;
+#[doc(hidden)]
+pub mod stand_in;
+#[cfg(all(feature = "unstable-websocket", not(target_family = "wasm")))]
+#[doc(hidden)]
+pub mod websocket;
 
-mod heartbeat;
 mod plugin;
 
+pub use all_devices::AllDevicesEffect;
 pub use bgr_color::BGRColor;
+pub use brightness::{ChromaBrightness, DimOnPause};
+pub use calibration::{ChromaCalibration, DeviceCalibration};
+pub use device_state::ChromaDeviceState;
+pub use devices::{ChromaDevices, DeviceStatus, MissingDevicePolicy};
+pub use diagnostics::ChromaDiagnosticsPlugin;
+pub use gradient::{Gradient, GradientColor};
+pub use grid::ColorGrid;
+pub use health::{ChromaHealth, ChromaHealthChanged};
+pub use heartbeat::ChromaHeartbeatSettings;
 pub use key_color::KeyColor;
+pub use preview::ChromaPreviewTransport;
+pub use replay::{ChromaReplay, ChromaReplayPlugin};
+#[cfg(all(feature = "unstable-websocket", not(target_family = "wasm")))]
+#[doc(hidden)]
+pub use websocket::ChromaWebSocketTransport;
 
 pub struct ChromaPlugin {
     settings: ChromaRunnerInitializationSettings,
+    calibration: ChromaCalibration,
+    heartbeat: ChromaHeartbeatSettings,
+    missing_device_policy: MissingDevicePolicy,
 }
 
 impl ChromaPlugin {
     #[must_use]
     pub fn new(settings: ChromaRunnerInitializationSettings) -> Self {
-        Self { settings }
+        Self {
+            settings,
+            calibration: ChromaCalibration::default(),
+            heartbeat: ChromaHeartbeatSettings::default(),
+            missing_device_policy: MissingDevicePolicy::default(),
+        }
+    }
+
+    /// Sets the initial [`ChromaCalibration`]. It can be changed later
+    /// through the resource.
+    #[must_use]
+    pub fn with_calibration(mut self, calibration: ChromaCalibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// Sets the initial [`ChromaHeartbeatSettings`]. They can be changed
+    /// later through the resource.
+    #[must_use]
+    pub fn with_heartbeat(mut self, heartbeat: ChromaHeartbeatSettings) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Sets the initial [`MissingDevicePolicy`]. It can be changed later
+    /// through the [`ChromaDevices`] resource.
+    #[must_use]
+    pub fn with_missing_device_policy(mut self, policy: MissingDevicePolicy) -> Self {
+        self.missing_device_policy = policy;
+        self
     }
 }
 
 #[derive(SystemParam)]
 pub struct Chroma<'w, 's> {
     commands: Commands<'w, 's>,
+    devices: Res<'w, ChromaDevices>,
 }
 
 impl<'w, 's> Chroma<'w, 's> {
@@ -44,6 +108,36 @@ impl<'w, 's> Chroma<'w, 's> {
         }
     }
 
+    /// Creates one effect for each enabled device, grouped under one handle so
+    /// that they are applied and deleted together.
+    #[must_use]
+    pub fn create_all_devices_effect(
+        &mut self,
+        effect: impl Into<AllDevicesEffect>,
+    ) -> EffectHandle {
+        let effect = effect.into();
+        let effects = self
+            .devices
+            .enabled()
+            .into_iter()
+            .filter_map(|device| effect.effect_for(device))
+            .map(|effect| self.commands.spawn(effect).id())
+            .collect();
+
+        EffectHandle {
+            entity: self.commands.spawn(EffectGroup { effects }).id(),
+        }
+    }
+
+    /// Creates and applies an effect on every enabled device, such as a
+    /// [`BGRColor`] to light them all with. The returned handle can be used
+    /// to apply the effects again, or to delete them.
+    pub fn apply_to_all(&mut self, effect: impl Into<AllDevicesEffect>) -> EffectHandle {
+        let effect_handle = self.create_all_devices_effect(effect);
+        self.apply_effect(&effect_handle);
+        effect_handle
+    }
+
     pub fn apply_effect_with_deadline(&mut self, effect_handle: &EffectHandle, deadline: Instant) {
         self.commands.spawn(ApplyEffectRequest {
             effect_entity: effect_handle.entity,
@@ -54,6 +148,13 @@ impl<'w, 's> Chroma<'w, 's> {
     pub fn apply_effect(&mut self, effect_handle: &EffectHandle) {
         self.apply_effect_with_deadline(effect_handle, Instant::now() + Duration::from_secs(60));
     }
+
+    /// Deletes the effect, or every effect of a group, from the server.
+    pub fn delete_effect(&mut self, effect_handle: EffectHandle) {
+        self.commands.spawn(DeleteEffectRequest {
+            effect_entity: effect_handle.entity,
+        });
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -122,6 +223,30 @@ pub enum SupportedDevice {
     ChromaLink,
 }
 
+impl SupportedDevice {
+    pub const ALL: [SupportedDevice; 6] = [
+        SupportedDevice::Keyboard,
+        SupportedDevice::Mouse,
+        SupportedDevice::Mousepad,
+        SupportedDevice::Headset,
+        SupportedDevice::Keypad,
+        SupportedDevice::ChromaLink,
+    ];
+
+    /// The path of this device's effect API, relative to the session URL.
+    #[must_use]
+    pub(crate) fn get_api(&self) -> &'static str {
+        match self {
+            SupportedDevice::Keyboard => "keyboard",
+            SupportedDevice::Mouse => "mouse",
+            SupportedDevice::Mousepad => "mousepad",
+            SupportedDevice::Headset => "headset",
+            SupportedDevice::Keypad => "keypad",
+            SupportedDevice::ChromaLink => "chromalink",
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Category {
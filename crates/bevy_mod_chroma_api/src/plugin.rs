@@ -1,28 +1,53 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
 use bevy::{
     log::*,
     prelude::{
-        in_state, resource_exists, App, Commands, Component, Condition, Entity, In, IntoSystem,
-        IntoSystemConfigs, Local, NextState, Plugin, Query, Res, ResMut, States, Without,
+        apply_deferred, in_state, resource_exists, App, Commands, Component, Condition, Entity, In,
+        IntoSystem, IntoSystemConfigs, Local, NextState, Plugin, Query, Res, ResMut, Resource,
+        States, Without,
     },
-    utils::Instant,
+    utils::{tracing::field, HashMap, Instant},
 };
 use bevy_mod_chroma_request_lib::{
-    ExecuteHttpRequests, HttpRequestError, HttpRequestHandle, HttpRequestPlugin, HttpRequestSet,
-    HttpRequests,
+    ExecuteHttpRequests, HttpRequestError, HttpRequestHandle, HttpRequestOptions,
+    HttpRequestPlugin, HttpRequestSet, HttpRequests, HttpRetryPolicy, TypedRequestHandle,
 };
 use serde::Serialize;
 
 use crate::{
-    api::{CreateEffectResponse, Effect, SessionInfo},
-    heartbeat::HeartbeatPlugin,
-    ChromaPlugin, ChromaRunner, ChromaRunnerInitializationSettings,
+    api::{CreateEffectResponse, Effect, ResultResponse, SessionInfo},
+    brightness::{BrightnessPlugin, EffectiveBrightness},
+    device_state::ChromaDeviceState,
+    devices::{ChromaDevices, DevicesPlugin},
+    diagnostics::EffectStats,
+    heartbeat::{HeartbeatPlugin, SessionActivity},
+    ChromaCalibration, ChromaPlugin, ChromaRunner, ChromaRunnerInitializationSettings,
+    DeviceCalibration, SupportedDevice,
 };
 
 impl Plugin for ChromaPlugin {
     fn build(&self, app: &mut App) {
+        // Apps can add their own `HttpRequestPlugin` first to configure it
+        if !app.is_plugin_added::<HttpRequestPlugin>() {
+            app.add_plugins(HttpRequestPlugin::default());
+        }
+
         app.insert_resource(self.settings.clone())
+            .insert_resource(self.calibration.clone())
+            .insert_resource(self.heartbeat)
+            .insert_resource(
+                ChromaDevices::new(&self.settings.init_request.device_supported)
+                    .with_missing_device_policy(self.missing_device_policy),
+            )
+            .init_resource::<LastAppliedEffects>()
+            .init_resource::<EffectStats>()
+            .init_resource::<ChromaDeviceState>()
             .add_state::<RunnerState>()
-            .add_plugins((HttpRequestPlugin, HeartbeatPlugin))
+            .add_plugins((HeartbeatPlugin, BrightnessPlugin, DevicesPlugin))
             .add_systems(
                 ExecuteHttpRequests,
                 (
@@ -30,7 +55,15 @@ impl Plugin for ChromaPlugin {
                         resource_exists::<ChromaRunnerInitializationSettings>()
                             .and_then(in_state(RunnerState::Init)),
                     ),
-                    system_create_pending_effects
+                    // Effects that start being created this frame must be
+                    // seen as in flight when deleting, so that their deletion
+                    // waits for their id instead of leaving them on the server
+                    (
+                        system_create_pending_effects,
+                        apply_deferred,
+                        system_delete_effects,
+                    )
+                        .chain()
                         .in_set(HttpRequestSet::BeforeExecuteRequests)
                         .run_if(
                             resource_exists::<ChromaRunner>()
@@ -54,6 +87,13 @@ impl Plugin for ChromaPlugin {
                             resource_exists::<ChromaRunner>()
                                 .and_then(in_state(RunnerState::Running)),
                         ),
+                    system_resend_on_preparation_change
+                        .in_set(HttpRequestSet::BeforeExecuteRequests)
+                        .run_if(
+                            resource_exists::<ChromaRunner>()
+                                .and_then(in_state(RunnerState::Running)),
+                        ),
+                    system_detached_requests_cleanup.in_set(HttpRequestSet::AfterGatherResponses),
                 ),
             );
     }
@@ -74,20 +114,20 @@ fn system_init_error_handler(
 ) {
     if let Err(err) = result {
         runner_state.set(RunnerState::Error);
-        error!("failed to initialize chroma runner: {:?}", err);
+        error!("failed to initialize chroma runner: {}", err);
     }
 }
 
 fn system_init(
     mut commands: Commands,
-    mut init_request: Local<Option<HttpRequestHandle>>,
+    mut init_request: Local<Option<TypedRequestHandle<SessionInfo>>>,
     mut requests: HttpRequests,
     mut runner_state: ResMut<NextState<RunnerState>>,
     init: Res<ChromaRunnerInitializationSettings>,
 ) -> Result<(), InitError> {
     if init_request.is_none() {
         *init_request = Some(
-            requests.request(
+            requests.request_typed(
                 requests
                     .client()
                     .post(init.init_url)
@@ -98,9 +138,8 @@ fn system_init(
         return Ok(());
     }
 
-    // SAFETY: init_request is always Some here as verified above
-    if let Some(response) = requests.get_response(init_request.as_ref().unwrap()) {
-        let session_info = response.as_ref()?.json::<SessionInfo>()?;
+    if let Some(session_info) = requests.poll_typed(&mut init_request) {
+        let session_info = session_info?;
         let root_url = if session_info.root_url.ends_with('/') {
             session_info.root_url
         } else {
@@ -112,10 +151,6 @@ fn system_init(
         });
         commands.remove_resource::<ChromaRunnerInitializationSettings>();
 
-        // SAFETY: as above, init_request is always Some here
-        let init_request = init_request.take().unwrap();
-        requests.dispose(init_request);
-
         runner_state.set(RunnerState::Running);
         info!("successfully opened chroma session to {}", root_url);
     }
@@ -126,19 +161,21 @@ fn system_init(
 #[derive(Debug)]
 enum InitError {
     Request(HttpRequestError),
-    Parse(serde_json::Error),
     Url(url::ParseError),
 }
 
-impl From<&HttpRequestError> for InitError {
-    fn from(error: &HttpRequestError) -> Self {
-        Self::Request(error.clone())
+impl Display for InitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(error) => write!(f, "request failed: {error}"),
+            Self::Url(error) => write!(f, "invalid session url: {error}"),
+        }
     }
 }
 
-impl From<serde_json::Error> for InitError {
-    fn from(error: serde_json::Error) -> Self {
-        Self::Parse(error)
+impl From<HttpRequestError> for InitError {
+    fn from(error: HttpRequestError) -> Self {
+        Self::Request(error)
     }
 }
 
@@ -148,36 +185,127 @@ impl From<url::ParseError> for InitError {
     }
 }
 
+/// How effects whose creation failed are created again. This is separate from
+/// the request retries, which only apply to idempotent requests.
+const CREATE_RETRY_POLICY: HttpRetryPolicy = HttpRetryPolicy {
+    max_attempts: 5,
+    initial_backoff: Duration::from_millis(500),
+    backoff_multiplier: 2.0,
+    max_backoff: Duration::from_secs(10),
+};
+
+/// Marks an effect whose creation has failed, so that it is only created
+/// again after a backoff, and not at all once it has failed too many times
+#[derive(Component)]
+struct FailedCreateEffect {
+    attempts: u32,
+    retry_after: Instant,
+}
+
+impl FailedCreateEffect {
+    #[must_use]
+    fn can_retry(&self) -> bool {
+        self.attempts < CREATE_RETRY_POLICY.max_attempts && Instant::now() >= self.retry_after
+    }
+}
+
 #[derive(Component)]
 struct InFlightCreateEffectRequest {
     request_handle: Option<HttpRequestHandle>,
+    preparation: Preparation,
 }
 
-#[derive(Component, Serialize)]
-struct CreatedEffect {
+#[derive(Component)]
+pub(crate) struct CreatedEffect {
     id: String,
+    /// How the effect was prepared when it was created on the server
+    preparation: Preparation,
 }
 
-#[allow(clippy::type_complexity)]
+/// The brightness and calibration that an effect is prepared with before it
+/// is sent to the server. Effects on the server are stale once the current
+/// preparation for their device no longer matches theirs.
+#[derive(Clone, Copy, PartialEq)]
+struct Preparation {
+    brightness: f32,
+    calibration: Option<DeviceCalibration>,
+}
+
+impl Preparation {
+    #[must_use]
+    fn current(
+        device: SupportedDevice,
+        brightness: &EffectiveBrightness,
+        calibration: &ChromaCalibration,
+    ) -> Self {
+        Self {
+            brightness: brightness.get(device),
+            calibration: calibration.get(device).copied(),
+        }
+    }
+}
+
+/// Scales and calibrates an effect into the form that is sent to the server
+#[must_use]
+fn prepare_effect(effect: &Effect, preparation: Preparation) -> Effect {
+    let Preparation {
+        brightness,
+        calibration,
+    } = preparation;
+    if brightness >= 1.0 && calibration.is_none() {
+        return *effect;
+    }
+
+    effect.map_colors(|color| {
+        let color = if brightness < 1.0 {
+            color.scale(brightness)
+        } else {
+            color
+        };
+
+        match calibration {
+            Some(calibration) => calibration.calibrate(color),
+            None => color,
+        }
+    })
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn system_create_pending_effects(
     mut commands: Commands,
     mut requests: HttpRequests,
     runner: Res<ChromaRunner>,
+    calibration: Res<ChromaCalibration>,
+    brightness: Res<EffectiveBrightness>,
+    devices: Res<ChromaDevices>,
     pending_effects: Query<
-        (Entity, &Effect),
+        (Entity, &Effect, Option<&FailedCreateEffect>),
         (Without<InFlightCreateEffectRequest>, Without<CreatedEffect>),
     >,
 ) {
-    for (entity, effect) in pending_effects.iter() {
+    for (entity, effect, failed) in pending_effects.iter() {
+        // Effects for missing devices are never applied, so aren't created
+        if devices.is_missing(effect.device()) {
+            continue;
+        }
+
+        if failed.is_some_and(|failed| !failed.can_retry()) {
+            continue;
+        }
+
+        let preparation = Preparation::current(effect.device(), &brightness, &calibration);
+
+        let _span = info_span!("chroma_create_effect", device = effect.get_api()).entered();
         let request_handle = requests.request(
             requests
                 .client()
                 .post(runner.get_session_url(effect.get_api()))
-                .json(effect),
+                .json(&prepare_effect(effect, preparation)),
         );
 
         commands.entity(entity).insert(InFlightCreateEffectRequest {
             request_handle: Some(request_handle),
+            preparation,
         });
     }
 }
@@ -185,31 +313,79 @@ fn system_create_pending_effects(
 fn system_gather_create_effect_results(
     mut commands: Commands,
     mut requests: HttpRequests,
+    mut activity: ResMut<SessionActivity>,
+    mut stats: ResMut<EffectStats>,
     mut in_flight_create_requests: Query<
-        (Entity, &mut InFlightCreateEffectRequest),
+        (
+            Entity,
+            &Effect,
+            &mut InFlightCreateEffectRequest,
+            Option<&FailedCreateEffect>,
+        ),
         Without<CreatedEffect>,
     >,
 ) {
-    for (entity, mut in_flight_request) in in_flight_create_requests.iter_mut() {
+    for (entity, effect, mut in_flight_request, failed) in in_flight_create_requests.iter_mut() {
         if let Some(result) =
             requests.get_response(in_flight_request.request_handle.as_ref().unwrap())
         {
-            // TODO error check result body
-            if let Ok(success_result) = result {
-                let id = success_result
-                    .json::<CreateEffectResponse>()
-                    .unwrap()
-                    .id()
-                    .into();
-
-                commands
-                    .entity(entity)
-                    .insert(CreatedEffect { id })
-                    .remove::<InFlightCreateEffectRequest>();
-            } else {
-                error!("failed to create effect: {:?}", result);
+            let span = info_span!(
+                "chroma_effect_created",
+                device = effect.get_api(),
+                effect_id = field::Empty,
+            )
+            .entered();
+            stats.creates_completed += 1;
+
+            match result
+                .as_ref()
+                .map_err(Clone::clone)
+                .and_then(|response| Ok(response.json::<CreateEffectResponse>()?))
+            {
+                Ok(response) if response.result().is_success() => {
+                    span.record("effect_id", response.id());
+                    activity.record();
+                    commands
+                        .entity(entity)
+                        .insert(CreatedEffect {
+                            id: response.id().into(),
+                            preparation: in_flight_request.preparation,
+                        })
+                        .remove::<FailedCreateEffect>();
+                }
+                failure => {
+                    let err = match failure {
+                        Ok(response) => {
+                            activity.record();
+                            format!("{:?}", response.result())
+                        }
+                        Err(err) => err.to_string(),
+                    };
+                    stats.creates_failed += 1;
+
+                    // The effect is left pending, so that it is created again
+                    // after backing off
+                    let attempts = failed.map_or(0, |failed| failed.attempts) + 1;
+                    if attempts < CREATE_RETRY_POLICY.max_attempts {
+                        error!("failed to create effect: {}", err);
+                    } else {
+                        error!(
+                            "failed to create effect, giving up after {} attempts: {}",
+                            attempts, err
+                        );
+                    }
+
+                    commands.entity(entity).insert(FailedCreateEffect {
+                        attempts,
+                        retry_after: Instant::now() + CREATE_RETRY_POLICY.backoff(attempts),
+                    });
+                }
             }
 
+            commands
+                .entity(entity)
+                .remove::<InFlightCreateEffectRequest>();
+
             let request_handle = in_flight_request.request_handle.take().unwrap();
             requests.dispose(request_handle);
         }
@@ -231,7 +407,14 @@ impl ApplyEffectRequest {
 
 #[derive(Component)]
 pub(crate) struct InFlightApplyEffectRequest {
-    request_handle: Option<HttpRequestHandle>,
+    /// One request per effect, as `PUT /effect` only takes a single id, along
+    /// with how the effect was prepared
+    request_handles: Vec<(
+        Entity,
+        Preparation,
+        Option<TypedRequestHandle<ResultResponse>>,
+    )>,
+    failed: bool,
     pub(crate) deadline: Instant,
 }
 
@@ -242,62 +425,477 @@ impl InFlightApplyEffectRequest {
     }
 }
 
+/// Effects that are created, applied and deleted together under one handle
+#[derive(Component)]
+pub(crate) struct EffectGroup {
+    pub(crate) effects: Vec<Entity>,
+}
+
+#[derive(Component)]
+pub(crate) struct DeleteEffectRequest {
+    pub(crate) effect_entity: Entity,
+}
+
+/// Returns the effects behind a handle's entity, which is either a single
+/// effect or an [`EffectGroup`]
+#[must_use]
+fn group_members<'a>(
+    effect_entity: &'a Entity,
+    groups_query: &'a Query<&EffectGroup>,
+) -> &'a [Entity] {
+    match groups_query.get(*effect_entity) {
+        Ok(group) => &group.effects,
+        Err(_) => std::slice::from_ref(effect_entity),
+    }
+}
+
+/// The body of requests that apply an effect by id
+#[derive(Serialize)]
+struct EffectId<'a> {
+    id: &'a str,
+}
+
+/// The body of requests that delete effects by id
+#[derive(Serialize)]
+#[serde(untagged)]
+enum EffectIds<'a> {
+    One { id: &'a str },
+    Many { ids: Vec<&'a str> },
+}
+
+impl<'a> EffectIds<'a> {
+    #[must_use]
+    fn new(mut ids: Vec<&'a str>) -> Self {
+        if ids.len() == 1 {
+            EffectIds::One { id: ids.remove(0) }
+        } else {
+            EffectIds::Many { ids }
+        }
+    }
+}
+
+/// The most recently applied effect for each device, so that it can be sent
+/// again when its brightness or calibration changes
+#[derive(Resource, Default)]
+struct LastAppliedEffects {
+    effects: HashMap<SupportedDevice, LastAppliedEffect>,
+}
+
+struct LastAppliedEffect {
+    effect_entity: Entity,
+    preparation: Preparation,
+    /// When the effect was last sent again because its preparation changed
+    resent_at: Option<Instant>,
+}
+
+/// How often the effect of a device is sent again while its preparation keeps
+/// changing, such as during a [`DimOnPause`](crate::DimOnPause) fade
+const RESEND_INTERVAL: Duration = Duration::from_millis(100);
+
+#[allow(clippy::too_many_arguments)]
 fn system_apply_effects(
     mut commands: Commands,
     mut requests: HttpRequests,
+    mut stats: ResMut<EffectStats>,
     runner: Res<ChromaRunner>,
+    calibration: Res<ChromaCalibration>,
+    brightness: Res<EffectiveBrightness>,
+    devices: Res<ChromaDevices>,
     requests_query: Query<(Entity, &ApplyEffectRequest)>,
-    effects_query: Query<&CreatedEffect>,
+    effects_query: Query<(&Effect, Option<&CreatedEffect>)>,
+    groups_query: Query<&EffectGroup>,
 ) {
     for (entity, application_request) in requests_query.iter() {
         if application_request.is_expired() {
             commands.entity(entity).despawn();
+            stats.applies_dropped += 1;
             continue;
         }
 
-        if let Ok(created_effect) = effects_query.get(application_request.effect_entity) {
-            let request_handle = requests.request(
-                requests
-                    .client()
-                    .put(runner.get_session_url("effect"))
-                    .json(created_effect),
+        let mut created_effects = Vec::new();
+        let mut missing_effects = Vec::new();
+        let mut ready = true;
+
+        for &effect_entity in group_members(&application_request.effect_entity, &groups_query) {
+            let Ok((effect, created_effect)) = effects_query.get(effect_entity) else {
+                ready = false;
+                continue;
+            };
+
+            // Effects for missing devices are never created
+            if devices.is_missing(effect.device()) {
+                missing_effects.push(effect);
+                continue;
+            }
+
+            match created_effect {
+                Some(created_effect) => {
+                    created_effects.push((effect_entity, effect, created_effect))
+                }
+                None => ready = false,
+            }
+        }
+
+        if !ready {
+            continue;
+        }
+
+        // Effects for missing devices are dropped or redirected instead. A
+        // redirect is skipped when the target device is already lit by an
+        // effect in the same apply, so that it isn't overwritten
+        let mut dropped_any = false;
+        for effect in missing_effects {
+            let Some(redirected) = devices.redirect(effect) else {
+                dropped_any = true;
+                continue;
+            };
+
+            let device = redirected.device();
+            if created_effects
+                .iter()
+                .any(|(_, created, _)| created.device() == device)
+            {
+                continue;
+            }
+
+            let _span = info_span!(
+                "chroma_redirect_effect",
+                device = effect.get_api(),
+                target = device.get_api(),
+            )
+            .entered();
+            send_to_device(
+                &mut commands,
+                &mut requests,
+                &runner,
+                &redirected,
+                Preparation::current(device, &brightness, &calibration),
+                "redirect effect",
             );
+        }
 
-            commands
-                .entity(entity)
-                .insert(InFlightApplyEffectRequest {
-                    request_handle: Some(request_handle),
-                    deadline: application_request.deadline,
-                })
-                .remove::<ApplyEffectRequest>();
+        if dropped_any {
+            stats.applies_dropped += 1;
         }
+
+        if created_effects.is_empty() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let _span = info_span!(
+            "chroma_apply_effect",
+            device = join(
+                created_effects
+                    .iter()
+                    .map(|(_, effect, _)| effect.get_api())
+            ),
+            effect_id = join(
+                created_effects
+                    .iter()
+                    .map(|(_, _, created)| created.id.as_str())
+            ),
+        )
+        .entered();
+        let request_handles = created_effects
+            .iter()
+            .map(|(effect_entity, effect, created_effect)| {
+                let preparation = Preparation::current(effect.device(), &brightness, &calibration);
+
+                // Effects created with an old brightness or calibration are
+                // sent straight to their device instead, rather than being
+                // created again on every change
+                let request = if created_effect.preparation == preparation {
+                    requests
+                        .client()
+                        .put(runner.get_session_url("effect"))
+                        .json(&EffectId {
+                            id: &created_effect.id,
+                        })
+                } else {
+                    requests
+                        .client()
+                        .put(runner.get_session_url(effect.get_api()))
+                        .json(&prepare_effect(effect, preparation))
+                };
+                let request_handle = requests.request_typed_with_options(
+                    request,
+                    HttpRequestOptions::default().idempotent(),
+                );
+
+                (*effect_entity, preparation, Some(request_handle))
+            })
+            .collect();
+
+        commands
+            .entity(entity)
+            .insert(InFlightApplyEffectRequest {
+                request_handles,
+                failed: false,
+                deadline: application_request.deadline,
+            })
+            .remove::<ApplyEffectRequest>();
     }
 }
 
+#[must_use]
+fn join<'a>(values: impl Iterator<Item = &'a str>) -> String {
+    values.collect::<Vec<_>>().join(",")
+}
+
+#[allow(clippy::too_many_arguments)]
 fn system_apply_effects_cleanup(
     mut commands: Commands,
     mut requests: HttpRequests,
+    mut activity: ResMut<SessionActivity>,
+    mut stats: ResMut<EffectStats>,
+    mut device_state: ResMut<ChromaDeviceState>,
+    mut last_applied_effects: ResMut<LastAppliedEffects>,
     mut in_flight_requests_query: Query<(Entity, &mut InFlightApplyEffectRequest)>,
+    effects_query: Query<&Effect>,
 ) {
     for (entity, mut in_flight_request) in in_flight_requests_query.iter_mut() {
         if in_flight_request.is_expired() {
-            let request_handle = in_flight_request.request_handle.take().unwrap();
-            requests.dispose(request_handle);
+            for (_, _, request_handle) in in_flight_request.request_handles.drain(..) {
+                requests.dispose_typed(request_handle.unwrap());
+            }
             commands.entity(entity).despawn();
+            stats.applies_dropped += 1;
             continue;
         }
 
-        if let Some(result) =
-            requests.get_response(in_flight_request.request_handle.as_ref().unwrap())
+        let in_flight_request = &mut *in_flight_request;
+        let mut failed = false;
+        in_flight_request.request_handles.retain_mut(
+            |(effect_entity, preparation, request_handle)| {
+                let Some(result) = requests.poll_typed(request_handle) else {
+                    return true;
+                };
+
+                match result {
+                    Ok(response) if response.result.is_success() => {
+                        activity.record();
+                        if let Ok(effect) = effects_query.get(*effect_entity) {
+                            device_state.record_applied(effect);
+                            last_applied_effects.effects.insert(
+                                effect.device(),
+                                LastAppliedEffect {
+                                    effect_entity: *effect_entity,
+                                    preparation: *preparation,
+                                    resent_at: None,
+                                },
+                            );
+                        }
+                    }
+                    Ok(response) => {
+                        activity.record();
+                        error!("failed to apply effect: {:?}", response.result);
+                        failed = true;
+                    }
+                    Err(err) => {
+                        error!("failed to apply effect: {}", err);
+                        failed = true;
+                    }
+                }
+
+                false
+            },
+        );
+        in_flight_request.failed |= failed;
+
+        // The apply only completes once every effect in it has been applied
+        if !in_flight_request.request_handles.is_empty() {
+            continue;
+        }
+
+        stats.applies_completed += 1;
+        if in_flight_request.failed {
+            stats.applies_failed += 1;
+        }
+
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Deletes effects from the server, and despawns them once none of them are
+/// still being created
+fn system_delete_effects(
+    mut commands: Commands,
+    mut requests: HttpRequests,
+    runner: Res<ChromaRunner>,
+    requests_query: Query<(Entity, &DeleteEffectRequest)>,
+    effects_query: Query<(Option<&CreatedEffect>, Option<&InFlightCreateEffectRequest>)>,
+    groups_query: Query<&EffectGroup>,
+) {
+    for (entity, delete_request) in requests_query.iter() {
+        let members = group_members(&delete_request.effect_entity, &groups_query);
+
+        // Effects still being created are deleted once they have an id,
+        // rather than being left behind on the server
+        if effects_query
+            .iter_many(members)
+            .any(|(_, in_flight)| in_flight.is_some())
         {
-            // TODO error check result body
-            if let Err(err) = result {
-                error!("failed to apply effect: {:?}", err);
-            }
+            continue;
+        }
 
-            let request_handle = in_flight_request.request_handle.take().unwrap();
-            requests.dispose(request_handle);
-            commands.entity(entity).despawn();
+        let ids: Vec<_> = effects_query
+            .iter_many(members)
+            .filter_map(|(created_effect, _)| created_effect)
+            .map(|created_effect| created_effect.id.as_str())
+            .collect();
+        if !ids.is_empty() {
+            delete_created_effects(&mut commands, &mut requests, &runner, ids);
+        }
+
+        for effect_entity in members {
+            commands.entity(*effect_entity).despawn();
+        }
+
+        if groups_query.contains(delete_request.effect_entity) {
+            commands.entity(delete_request.effect_entity).despawn();
+        }
+
+        commands.entity(entity).despawn();
+    }
+}
+
+fn delete_created_effects(
+    commands: &mut Commands,
+    requests: &mut HttpRequests,
+    runner: &ChromaRunner,
+    ids: Vec<&str>,
+) {
+    let _span = info_span!(
+        "chroma_delete_effect",
+        effect_id = join(ids.iter().copied())
+    )
+    .entered();
+    let request_handle = requests.request_typed_with_options(
+        requests
+            .client()
+            .delete(runner.get_session_url("effect"))
+            .json(&EffectIds::new(ids)),
+        HttpRequestOptions::default().idempotent(),
+    );
+
+    commands.spawn(DetachedRequest {
+        request_handle: Some(request_handle),
+        description: "delete effect",
+        applied_effect: None,
+    });
+}
+
+/// Sends the last applied effect of each device again, directly to the device
+/// API, when the brightness or calibration of that device has changed. While
+/// the brightness keeps changing, this happens at most once every
+/// [`RESEND_INTERVAL`], and the latest brightness is sent once it settles.
+#[allow(clippy::too_many_arguments)]
+fn system_resend_on_preparation_change(
+    mut commands: Commands,
+    mut requests: HttpRequests,
+    mut last_applied_effects: ResMut<LastAppliedEffects>,
+    runner: Res<ChromaRunner>,
+    calibration: Res<ChromaCalibration>,
+    brightness: Res<EffectiveBrightness>,
+    effects_query: Query<&Effect>,
+) {
+    for (device, last_applied_effect) in last_applied_effects.effects.iter_mut() {
+        let preparation = Preparation::current(*device, &brightness, &calibration);
+        if last_applied_effect.preparation == preparation {
+            continue;
+        }
+
+        if last_applied_effect
+            .resent_at
+            .is_some_and(|resent_at| resent_at.elapsed() < RESEND_INTERVAL)
+        {
+            continue;
+        }
+
+        let Ok(effect) = effects_query.get(last_applied_effect.effect_entity) else {
+            continue;
+        };
+
+        last_applied_effect.preparation = preparation;
+        last_applied_effect.resent_at = Some(Instant::now());
+
+        let _span = info_span!("chroma_resend_effect", device = effect.get_api()).entered();
+        send_to_device(
+            &mut commands,
+            &mut requests,
+            &runner,
+            effect,
+            preparation,
+            "resend effect",
+        );
+    }
+}
+
+/// Sends an effect directly to its device API, without creating it first.
+/// Once the server has applied it, `effect` is recorded in
+/// [`ChromaDeviceState`].
+fn send_to_device(
+    commands: &mut Commands,
+    requests: &mut HttpRequests,
+    runner: &ChromaRunner,
+    effect: &Effect,
+    preparation: Preparation,
+    description: &'static str,
+) {
+    let request_handle = requests.request_typed_with_options(
+        requests
+            .client()
+            .put(runner.get_session_url(effect.get_api()))
+            .json(&prepare_effect(effect, preparation)),
+        HttpRequestOptions::default().idempotent(),
+    );
+
+    commands.spawn(DetachedRequest {
+        request_handle: Some(request_handle),
+        description,
+        applied_effect: Some(*effect),
+    });
+}
+
+/// A request whose response we don't need, other than to log failures
+#[derive(Component)]
+struct DetachedRequest {
+    request_handle: Option<TypedRequestHandle<ResultResponse>>,
+    description: &'static str,
+    /// The effect that the request applies to its device, if any
+    applied_effect: Option<Effect>,
+}
+
+fn system_detached_requests_cleanup(
+    mut commands: Commands,
+    mut requests: HttpRequests,
+    mut activity: ResMut<SessionActivity>,
+    mut device_state: ResMut<ChromaDeviceState>,
+    mut detached_requests_query: Query<(Entity, &mut DetachedRequest)>,
+) {
+    for (entity, mut detached_request) in detached_requests_query.iter_mut() {
+        let Some(result) = requests.poll_typed(&mut detached_request.request_handle) else {
+            continue;
+        };
+
+        match result {
+            Ok(response) if response.result.is_success() => {
+                activity.record();
+                if let Some(effect) = &detached_request.applied_effect {
+                    device_state.record_applied(effect);
+                }
+            }
+            Ok(response) => {
+                activity.record();
+                error!(
+                    "failed to {}: {:?}",
+                    detached_request.description, response.result
+                );
+            }
+            Err(err) => error!("failed to {}: {}", detached_request.description, err),
         }
+
+        commands.entity(entity).despawn();
     }
 }
@@ -0,0 +1,315 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "preview-png")]
+use std::{fs::File, io::BufWriter, path::PathBuf};
+
+use bevy::{log::*, utils::HashMap};
+use bevy_mod_chroma_request_lib::{HttpResponse, HttpTransport, TransportFuture};
+use reqwest::{Method, Request, StatusCode};
+use serde_json::Value;
+
+use crate::{
+    api::{Effect, RzResult},
+    grid::ColorGrid,
+    stand_in::{self, SessionEndpoint, StandInRoute},
+    SupportedDevice,
+};
+
+/// The host of the session URL handed out by the preview, which only needs
+/// to be recognised by the preview itself.
+const SESSION_HOST: &str = "http://chroma-preview.invalid";
+
+/// Answers the Chroma REST API in memory and draws every applied effect,
+/// so that lighting can be reviewed without Razer hardware.
+///
+/// Effects are drawn either as truecolor ANSI blocks in a terminal, or, with
+/// the `preview-png` feature, as PNG frames written to a directory.
+///
+/// Use it by inserting
+/// [`HttpRequestTransport::new(ChromaPreviewTransport::terminal())`](bevy_mod_chroma_request_lib::HttpRequestTransport::new).
+pub struct ChromaPreviewTransport {
+    state: Arc<Mutex<PreviewState>>,
+}
+
+enum PreviewOutput {
+    Terminal(Box<dyn Write + Send>),
+    #[cfg(feature = "preview-png")]
+    Png {
+        directory: PathBuf,
+        cell_size: u32,
+    },
+}
+
+struct PreviewState {
+    output: PreviewOutput,
+    effects: HashMap<String, Effect>,
+    next_effect_id: u64,
+    tick: u32,
+    /// The last effect drawn for each device
+    devices: BTreeMap<SupportedDevice, ColorGrid>,
+    frame: u64,
+    /// How many lines were drawn to the terminal last time, so that the next
+    /// frame can be drawn over them
+    lines_drawn: usize,
+}
+
+impl ChromaPreviewTransport {
+    /// Draws effects to standard output, redrawing over the previous frame.
+    #[must_use]
+    pub fn terminal() -> Self {
+        Self::to_writer(io::stdout())
+    }
+
+    /// Draws effects as ANSI text to `writer`, redrawing over the previous
+    /// frame.
+    #[must_use]
+    pub fn to_writer(writer: impl Write + Send + 'static) -> Self {
+        Self::new(PreviewOutput::Terminal(Box::new(writer)))
+    }
+
+    /// Writes each applied effect to `directory` as a PNG file named after
+    /// the frame number and device, such as `000001_keyboard.png`.
+    #[cfg(feature = "preview-png")]
+    #[must_use]
+    pub fn png(directory: impl Into<PathBuf>) -> Self {
+        Self::new(PreviewOutput::Png {
+            directory: directory.into(),
+            cell_size: 16,
+        })
+    }
+
+    /// Sets the width and height in pixels that each LED is drawn with in PNG
+    /// frames. Defaults to 16.
+    #[cfg(feature = "preview-png")]
+    #[must_use]
+    pub fn with_cell_size(self, size: u32) -> Self {
+        if let PreviewOutput::Png { cell_size, .. } = &mut self.state.lock().unwrap().output {
+            *cell_size = size.max(1);
+        }
+        self
+    }
+
+    #[must_use]
+    fn new(output: PreviewOutput) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PreviewState {
+                output,
+                effects: HashMap::default(),
+                next_effect_id: 0,
+                tick: 0,
+                devices: BTreeMap::new(),
+                frame: 0,
+                lines_drawn: 0,
+            })),
+        }
+    }
+}
+
+impl HttpTransport for ChromaPreviewTransport {
+    fn send(&self, request: Request) -> TransportFuture {
+        let state = self.state.clone();
+
+        // Drawing can be slow, especially to PNG, so it happens on the
+        // `IoTaskPool` rather than in the system sending the request
+        Box::pin(async move {
+            let body = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .unwrap_or_default();
+
+            let (status, body) =
+                state
+                    .lock()
+                    .unwrap()
+                    .route(request.method(), request.url().path(), body);
+
+            Ok(HttpResponse::new(status, body.to_string().into()))
+        })
+    }
+}
+
+impl PreviewState {
+    /// Answers a request the same way as the mock Chroma server, as both
+    /// route through [`stand_in`], but draws effects as they are applied.
+    /// There is only ever one session, so any session id is accepted.
+    fn route(&mut self, method: &Method, path: &str, body: &[u8]) -> (StatusCode, Value) {
+        let json = serde_json::from_slice(body).unwrap_or(Value::Null);
+
+        let endpoint = match stand_in::route(method.as_str(), path, &json) {
+            StandInRoute::Init => {
+                let uri = format!("{SESSION_HOST}{}", stand_in::session_path(1));
+                return (StatusCode::OK, stand_in::init_response(1, &uri));
+            }
+            StandInRoute::Session { endpoint, .. } => endpoint,
+            StandInRoute::MethodNotAllowed => return (StatusCode::METHOD_NOT_ALLOWED, Value::Null),
+            StandInRoute::NotFound => return (StatusCode::NOT_FOUND, Value::Null),
+        };
+
+        let result = match endpoint {
+            SessionEndpoint::Close => RzResult::Success,
+            SessionEndpoint::Heartbeat => {
+                self.tick += 1;
+                return (StatusCode::OK, stand_in::heartbeat_response(self.tick));
+            }
+            SessionEndpoint::ApplyEffect(Some(id)) => match self.effects.get(id).copied() {
+                Some(effect) => {
+                    self.draw(&effect);
+                    RzResult::Success
+                }
+                None => RzResult::NotFound,
+            },
+            SessionEndpoint::ApplyEffect(None) => RzResult::InvalidParameter,
+            SessionEndpoint::DeleteEffects(ids) => {
+                let mut result = RzResult::Success;
+                for id in ids {
+                    if self.effects.remove(id).is_none() {
+                        result = RzResult::NotFound;
+                    }
+                }
+
+                result
+            }
+            SessionEndpoint::CreateEffect(device) => {
+                let Ok(effect) = parse_effect(device, body) else {
+                    return (
+                        StatusCode::OK,
+                        stand_in::result_response(RzResult::InvalidParameter),
+                    );
+                };
+
+                self.next_effect_id += 1;
+                let id = stand_in::effect_id(self.next_effect_id);
+                self.effects.insert(id.clone(), effect);
+
+                return (StatusCode::OK, stand_in::create_effect_response(&id));
+            }
+            SessionEndpoint::SetEffect(device) => match parse_effect(device, body) {
+                Ok(effect) => {
+                    self.draw(&effect);
+                    RzResult::Success
+                }
+                Err(_) => RzResult::InvalidParameter,
+            },
+        };
+
+        (StatusCode::OK, stand_in::result_response(result))
+    }
+
+    fn draw(&mut self, effect: &Effect) {
+        let device = effect.device();
+        self.devices.insert(device, effect.to_grid());
+        self.frame += 1;
+
+        let result = match &mut self.output {
+            PreviewOutput::Terminal(writer) => {
+                let (text, lines) = render_ansi(&self.devices, self.lines_drawn);
+                self.lines_drawn = lines;
+
+                writer
+                    .write_all(text.as_bytes())
+                    .and_then(|_| writer.flush())
+            }
+            #[cfg(feature = "preview-png")]
+            PreviewOutput::Png {
+                directory,
+                cell_size,
+            } => write_png(
+                &directory.join(format!("{:06}_{}.png", self.frame, device.get_api())),
+                &self.devices[&device],
+                *cell_size,
+            ),
+        };
+
+        if let Err(err) = result {
+            warn!("failed to draw chroma preview: {}", err);
+        }
+    }
+}
+
+/// Parses the body sent to a device API, whose shape depends on the device.
+fn parse_effect(device: SupportedDevice, body: &[u8]) -> serde_json::Result<Effect> {
+    Ok(match device {
+        SupportedDevice::Keyboard => Effect::Keyboard(serde_json::from_slice(body)?),
+        SupportedDevice::Mouse => Effect::Mouse(serde_json::from_slice(body)?),
+        SupportedDevice::Mousepad => Effect::Mousepad(serde_json::from_slice(body)?),
+        SupportedDevice::Headset => Effect::Headset(serde_json::from_slice(body)?),
+        SupportedDevice::Keypad => Effect::Keypad(serde_json::from_slice(body)?),
+        SupportedDevice::ChromaLink => Effect::ChromaLink(serde_json::from_slice(body)?),
+    })
+}
+
+/// Draws every device's grid as rows of colored blocks, first moving the
+/// cursor up over the `previous_lines` of the last frame. Returns the text and
+/// how many lines it takes up.
+#[must_use]
+fn render_ansi(
+    devices: &BTreeMap<SupportedDevice, ColorGrid>,
+    previous_lines: usize,
+) -> (String, usize) {
+    let mut text = String::new();
+    if previous_lines > 0 {
+        let _ = write!(text, "\x1b[{previous_lines}A");
+    }
+
+    let mut lines = 0;
+    for (device, grid) in devices {
+        for (index, row) in grid.iter_rows().enumerate() {
+            let label = if index == 0 { device.get_api() } else { "" };
+            let _ = write!(text, "\r{label:<11}");
+
+            for color in row {
+                let _ = write!(
+                    text,
+                    "\x1b[48;2;{};{};{}m  ",
+                    color.r(),
+                    color.g(),
+                    color.b()
+                );
+            }
+
+            text.push_str("\x1b[0m\x1b[K\n");
+            lines += 1;
+        }
+    }
+
+    (text, lines)
+}
+
+#[cfg(feature = "preview-png")]
+fn write_png(path: &std::path::Path, grid: &ColorGrid, cell_size: u32) -> io::Result<()> {
+    let cell_size = cell_size as usize;
+    let width = grid.columns() * cell_size;
+    let height = grid.rows() * cell_size;
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for row in grid.iter_rows() {
+        let mut line = Vec::with_capacity(width * 3);
+        for color in row {
+            for _ in 0..cell_size {
+                line.extend([color.r() as u8, color.g() as u8, color.b() as u8]);
+            }
+        }
+
+        for _ in 0..cell_size {
+            pixels.extend_from_slice(&line);
+        }
+    }
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+}
@@ -0,0 +1,310 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    log::*,
+    prelude::{App, IntoSystemConfigs, Plugin, ResMut, Resource},
+    utils::{HashMap, HashSet, Instant},
+};
+use bevy_mod_chroma_request_lib::{
+    ExecuteHttpRequests, HttpRequestHandle, HttpRequestPlugin, HttpRequestSet, HttpRequests,
+    RecordedExchange, RecordedResponse,
+};
+use reqwest::{Method, Url};
+use serde_json::Value;
+
+/// Replays a recording made with
+/// [`RecordingTransport`](bevy_mod_chroma_request_lib::RecordingTransport),
+/// keeping the original timing.
+///
+/// The recorded init request is sent to `init_url`, and the session URLs and
+/// effect ids in later requests are rewritten to the ones handed out by the
+/// server being replayed against. Use this instead of `ChromaPlugin`, not
+/// alongside it.
+pub struct ChromaReplayPlugin {
+    init_url: Url,
+    exchanges: Vec<RecordedExchange>,
+}
+
+impl ChromaReplayPlugin {
+    /// # Panics
+    ///
+    /// Panics if `init_url` is not a valid URL.
+    #[must_use]
+    pub fn new(init_url: &str, exchanges: Vec<RecordedExchange>) -> Self {
+        Self {
+            init_url: init_url.try_into().expect("valid init url"),
+            exchanges,
+        }
+    }
+}
+
+impl Plugin for ChromaReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<HttpRequestPlugin>() {
+            app.add_plugins(HttpRequestPlugin::default());
+        }
+
+        let mut exchanges = self.exchanges.clone();
+        exchanges.sort_by_key(|exchange| exchange.timestamp_ms);
+
+        app.insert_resource(ChromaReplay {
+            init_url: self.init_url.clone(),
+            pending: exchanges.into(),
+            started_at: None,
+            failed: false,
+            session_urls: HashMap::default(),
+            effect_ids: HashMap::default(),
+            unresolved_effect_ids: HashSet::default(),
+            in_flight: Vec::new(),
+        })
+        .add_systems(
+            ExecuteHttpRequests,
+            (
+                system_replay_send.in_set(HttpRequestSet::BeforeExecuteRequests),
+                system_replay_gather.in_set(HttpRequestSet::AfterGatherResponses),
+            ),
+        );
+    }
+}
+
+/// Progress of a replay started by [`ChromaReplayPlugin`].
+#[derive(Resource)]
+pub struct ChromaReplay {
+    init_url: Url,
+    pending: VecDeque<RecordedExchange>,
+    started_at: Option<Instant>,
+    failed: bool,
+    /// Recorded session paths, mapped to the session URLs of the replay
+    session_urls: HashMap<String, Option<Url>>,
+    /// Recorded effect ids, mapped to the ids created during the replay
+    effect_ids: HashMap<String, String>,
+    /// Recorded effect ids whose replayed creation hasn't finished yet
+    unresolved_effect_ids: HashSet<String>,
+    in_flight: Vec<InFlightReplayRequest>,
+}
+
+impl ChromaReplay {
+    /// Whether every request has been sent and answered, or the replay has
+    /// failed.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.failed || (self.pending.is_empty() && self.in_flight.is_empty())
+    }
+
+    /// Whether the replay stopped early because a session could not be
+    /// initialized.
+    #[must_use]
+    pub fn has_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// The number of recorded requests that have not been sent yet.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the URL to replay a recorded request to, or `None` if the
+    /// session it belongs to hasn't been initialized yet.
+    #[must_use]
+    fn rewrite_url(&self, exchange: &RecordedExchange, kind: &ReplayKind) -> Option<Url> {
+        if let ReplayKind::Init { .. } = kind {
+            return Some(self.init_url.clone());
+        }
+
+        let session = self.session_urls.iter().find(|(recorded_path, _)| {
+            exchange.path == **recorded_path
+                || exchange.path.starts_with(&format!("{recorded_path}/"))
+        });
+
+        match session {
+            Some((recorded_path, session_url)) => {
+                let session_url = session_url.as_ref()?;
+                let rest = exchange.path[recorded_path.len()..].trim_start_matches('/');
+                let base = session_url.as_str().trim_end_matches('/');
+
+                if rest.is_empty() {
+                    base.try_into().ok()
+                } else {
+                    format!("{base}/{rest}").as_str().try_into().ok()
+                }
+            }
+            None => self.init_url.join(&exchange.path).ok(),
+        }
+    }
+
+    /// Returns the body to replay, with effect ids rewritten, or `None` if it
+    /// refers to an effect that is still being created.
+    #[must_use]
+    fn rewrite_body(&self, body: &Value) -> Option<Value> {
+        let mut body = body.clone();
+
+        let rewrite = |id: &mut Value| -> Option<()> {
+            if let Some(recorded_id) = id.as_str() {
+                if self.unresolved_effect_ids.contains(recorded_id) {
+                    return None;
+                }
+
+                if let Some(replayed_id) = self.effect_ids.get(recorded_id) {
+                    *id = Value::String(replayed_id.clone());
+                }
+            }
+
+            Some(())
+        };
+
+        if let Some(id) = body.get_mut("id") {
+            rewrite(id)?;
+        }
+
+        if let Some(Value::Array(ids)) = body.get_mut("ids") {
+            for id in ids {
+                rewrite(id)?;
+            }
+        }
+
+        Some(body)
+    }
+}
+
+struct InFlightReplayRequest {
+    request_handle: HttpRequestHandle,
+    kind: ReplayKind,
+}
+
+enum ReplayKind {
+    Init { recorded_path: String },
+    CreateEffect { recorded_id: String },
+    Other,
+}
+
+impl ReplayKind {
+    /// Works out what a recorded request was from its recorded response
+    #[must_use]
+    fn of(exchange: &RecordedExchange) -> Self {
+        let RecordedResponse::Received { body, .. } = &exchange.response else {
+            return Self::Other;
+        };
+
+        if let (Some(uri), true) = (body["uri"].as_str(), body.get("sessionid").is_some()) {
+            if let Ok(uri) = Url::parse(uri) {
+                return Self::Init {
+                    recorded_path: uri.path().trim_end_matches('/').to_owned(),
+                };
+            }
+        }
+
+        if let (Some(id), "POST") = (body["id"].as_str(), exchange.method.as_str()) {
+            return Self::CreateEffect {
+                recorded_id: id.to_owned(),
+            };
+        }
+
+        Self::Other
+    }
+}
+
+fn system_replay_send(mut replay: ResMut<ChromaReplay>, mut requests: HttpRequests) {
+    if replay.failed {
+        return;
+    }
+
+    let started_at = *replay.started_at.get_or_insert_with(Instant::now);
+    let elapsed_ms = started_at.elapsed().as_millis() as u64;
+
+    while let Some(exchange) = replay.pending.front() {
+        if exchange.timestamp_ms > elapsed_ms {
+            break;
+        }
+
+        let kind = ReplayKind::of(exchange);
+
+        // Requests that depend on a session or effect that is still being set
+        // up wait for it, holding back everything after them too
+        let Some(url) = replay.rewrite_url(exchange, &kind) else {
+            break;
+        };
+        let Some(body) = replay.rewrite_body(&exchange.body) else {
+            break;
+        };
+
+        let exchange = replay
+            .pending
+            .pop_front()
+            .expect("loop condition guarantees there is a front element");
+
+        let Ok(method) = Method::from_bytes(exchange.method.as_bytes()) else {
+            warn!("skipping replay of request with method {}", exchange.method);
+            continue;
+        };
+
+        let mut request = requests.client().request(method, url);
+        if !body.is_null() {
+            request = request.json(&body);
+        }
+
+        match &kind {
+            ReplayKind::Init { recorded_path } => {
+                replay.session_urls.insert(recorded_path.clone(), None);
+            }
+            ReplayKind::CreateEffect { recorded_id } => {
+                replay.unresolved_effect_ids.insert(recorded_id.clone());
+            }
+            ReplayKind::Other => {}
+        }
+
+        let request_handle = requests.request(request);
+        replay.in_flight.push(InFlightReplayRequest {
+            request_handle,
+            kind,
+        });
+    }
+}
+
+fn system_replay_gather(mut replay: ResMut<ChromaReplay>, mut requests: HttpRequests) {
+    let replay = replay.as_mut();
+    let mut index = 0;
+
+    while index < replay.in_flight.len() {
+        let in_flight = &replay.in_flight[index];
+        let Some(result) = requests.get_response(&in_flight.request_handle) else {
+            index += 1;
+            continue;
+        };
+
+        let body = match result {
+            Ok(response) => response.json::<Value>().unwrap_or(Value::Null),
+            Err(err) => {
+                warn!("replayed request failed: {}", err);
+                Value::Null
+            }
+        };
+
+        match &in_flight.kind {
+            ReplayKind::Init { recorded_path } => match body["uri"].as_str().map(Url::parse) {
+                Some(Ok(session_url)) => {
+                    replay
+                        .session_urls
+                        .insert(recorded_path.clone(), Some(session_url));
+                }
+                _ => {
+                    error!("failed to initialize replay session: {}", body);
+                    replay.failed = true;
+                }
+            },
+            ReplayKind::CreateEffect { recorded_id } => {
+                replay.unresolved_effect_ids.remove(recorded_id);
+
+                if let Some(replayed_id) = body["id"].as_str() {
+                    replay
+                        .effect_ids
+                        .insert(recorded_id.clone(), replayed_id.to_owned());
+                }
+            }
+            ReplayKind::Other => {}
+        }
+
+        let in_flight = replay.in_flight.swap_remove(index);
+        requests.dispose(in_flight.request_handle);
+    }
+}
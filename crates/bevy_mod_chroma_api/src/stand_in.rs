@@ -0,0 +1,173 @@
+//! Endpoint routing and response bodies for stand-ins of the Chroma REST
+//! server.
+//!
+//! Both [`ChromaPreviewTransport`](crate::ChromaPreviewTransport) and the
+//! `bevy_mod_chroma_mock` crate answer the same API, so they route requests
+//! and shape their responses here rather than each keeping their own copy.
+//! What they do with each request, such as drawing or recording it, is up to
+//! them.
+
+use serde_json::{json, Value};
+
+use crate::{api::RzResult, SupportedDevice};
+
+/// A request to the Chroma REST server, as routed by [`route`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StandInRoute<'a> {
+    /// `POST /razer/chromasdk`, which opens a session.
+    Init,
+    /// A request under `/sessions/{session_id}/chromasdk`.
+    Session {
+        session_id: u32,
+        endpoint: SessionEndpoint<'a>,
+    },
+    /// A known path with the wrong method.
+    MethodNotAllowed,
+    NotFound,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEndpoint<'a> {
+    /// `DELETE` on the session itself.
+    Close,
+    /// `PUT heartbeat`.
+    Heartbeat,
+    /// `PUT effect`, which only takes a single id, unlike deleting. `None` if
+    /// the body has no id.
+    ApplyEffect(Option<&'a str>),
+    /// `DELETE effect`, with the ids in either an `{"id": ...}` or
+    /// `{"ids": [...]}` body.
+    DeleteEffects(Vec<&'a str>),
+    /// `POST` to a device API.
+    CreateEffect(SupportedDevice),
+    /// `PUT` to a device API.
+    SetEffect(SupportedDevice),
+}
+
+/// Routes a request by its method, URL path and JSON body.
+#[must_use]
+pub fn route<'a>(method: &str, path: &str, body: &'a Value) -> StandInRoute<'a> {
+    if path.trim_end_matches('/') == "/razer/chromasdk" {
+        return match method {
+            "POST" => StandInRoute::Init,
+            _ => StandInRoute::MethodNotAllowed,
+        };
+    }
+
+    let Some((session_id, endpoint)) = parse_session_path(path) else {
+        return StandInRoute::NotFound;
+    };
+
+    let device = SupportedDevice::ALL
+        .into_iter()
+        .find(|device| device.get_api() == endpoint);
+
+    let endpoint = match (method, endpoint, device) {
+        ("DELETE", "", _) => SessionEndpoint::Close,
+        ("PUT", "heartbeat", _) => SessionEndpoint::Heartbeat,
+        ("PUT", "effect", _) => SessionEndpoint::ApplyEffect(body["id"].as_str()),
+        ("DELETE", "effect", _) => SessionEndpoint::DeleteEffects(effect_ids(body).collect()),
+        ("POST", _, Some(device)) => SessionEndpoint::CreateEffect(device),
+        ("PUT", _, Some(device)) => SessionEndpoint::SetEffect(device),
+        _ => return StandInRoute::NotFound,
+    };
+
+    StandInRoute::Session {
+        session_id,
+        endpoint,
+    }
+}
+
+/// The session path that [`route`] recognises, to be appended to the
+/// server's address in [`init_response`].
+#[must_use]
+pub fn session_path(session_id: u32) -> String {
+    format!("/sessions/{session_id}/chromasdk")
+}
+
+/// Splits `/sessions/{id}/chromasdk/{endpoint}` into the session id and
+/// endpoint.
+#[must_use]
+fn parse_session_path(path: &str) -> Option<(u32, &str)> {
+    let rest = path.strip_prefix("/sessions/")?;
+    let (session_id, rest) = rest.split_once('/')?;
+    let endpoint = rest.strip_prefix("chromasdk")?.trim_matches('/');
+
+    Some((session_id.parse().ok()?, endpoint))
+}
+
+/// Returns the effect ids in either an `{"id": ...}` or `{"ids": [...]}` body.
+fn effect_ids(body: &Value) -> impl Iterator<Item = &str> {
+    let single = body["id"].as_str();
+    let many = body["ids"].as_array().into_iter().flatten();
+
+    single.into_iter().chain(many.filter_map(Value::as_str))
+}
+
+/// The id given to the `number`th effect created, in the UUID form that the
+/// Chroma SDK uses.
+#[must_use]
+pub fn effect_id(number: u64) -> String {
+    format!("{number:08x}-0000-4000-8000-000000000000")
+}
+
+#[must_use]
+pub fn init_response(session_id: u32, uri: &str) -> Value {
+    json!({ "sessionid": session_id, "uri": uri })
+}
+
+#[must_use]
+pub fn heartbeat_response(tick: u32) -> Value {
+    json!({ "tick": tick })
+}
+
+#[must_use]
+pub fn result_response(result: RzResult) -> Value {
+    json!({ "result": result.code() })
+}
+
+#[must_use]
+pub fn create_effect_response(id: &str) -> Value {
+    json!({ "result": RzResult::Success.code(), "id": id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_session_endpoints() {
+        let body = json!({ "ids": ["a", "b"] });
+        assert_eq!(
+            route("DELETE", "/sessions/3/chromasdk/effect", &body),
+            StandInRoute::Session {
+                session_id: 3,
+                endpoint: SessionEndpoint::DeleteEffects(vec!["a", "b"]),
+            }
+        );
+
+        let body = Value::Null;
+        assert_eq!(
+            route("POST", "/sessions/1/chromasdk/mouse", &body),
+            StandInRoute::Session {
+                session_id: 1,
+                endpoint: SessionEndpoint::CreateEffect(SupportedDevice::Mouse),
+            }
+        );
+        assert_eq!(
+            route("PUT", "/sessions/1/chromasdk/effect", &body),
+            StandInRoute::Session {
+                session_id: 1,
+                endpoint: SessionEndpoint::ApplyEffect(None),
+            }
+        );
+        assert_eq!(
+            route("GET", "/razer/chromasdk/", &body),
+            StandInRoute::MethodNotAllowed
+        );
+        assert_eq!(
+            route("POST", "/sessions/1/chromasdk/toaster", &body),
+            StandInRoute::NotFound
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::utils::{HashMap, Instant};
+use bevy_mod_chroma_request_lib::{HttpResponse, HttpTransport, ReqwestTransport, TransportFuture};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::{Method, Request, StatusCode, Url};
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::SupportedDevice;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Sends effect frames over one WebSocket per Chroma session, and every other
+/// request over REST.
+///
+/// Effect frames are the `PUT` requests that set a device effect or apply a
+/// created one. Each is sent as a text message of the form
+/// `{"endpoint": "mouse", "body": {...}}` to the session URL with a `ws`
+/// scheme, and the next text message received is taken as its response body.
+///
+/// This is unstable and not part of the public API: the Chroma SDK doesn't
+/// document a WebSocket API, and this frame format is only understood by test
+/// servers such as the one in this crate's tests. It is only built with the
+/// `unstable-websocket` feature, until a real protocol is confirmed.
+///
+/// If the socket can't be opened within the reply timeout, or fails, the
+/// frame is sent over REST instead and the socket isn't tried again until the
+/// reconnect delay has passed.
+///
+/// Use it by inserting
+/// [`HttpRequestTransport::new(ChromaWebSocketTransport::default())`](bevy_mod_chroma_request_lib::HttpRequestTransport::new).
+pub struct ChromaWebSocketTransport {
+    fallback: Arc<dyn HttpTransport>,
+    reconnect_delay: Duration,
+    reply_timeout: Duration,
+    sessions: Mutex<HashMap<Url, Arc<SessionSocket>>>,
+}
+
+impl ChromaWebSocketTransport {
+    /// Sends requests that aren't effect frames, and frames that can't go over
+    /// the socket, with `fallback` instead of a [`ReqwestTransport`].
+    #[must_use]
+    pub fn with_fallback(mut self, fallback: impl HttpTransport) -> Self {
+        self.fallback = Arc::new(fallback);
+        self
+    }
+
+    /// How long to wait before opening a socket again after one fails.
+    /// Defaults to 5 seconds.
+    #[must_use]
+    pub fn with_reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    /// How long to wait for the socket to open, and then for the reply to a
+    /// frame, before giving up on the socket and sending the frame over REST.
+    /// Defaults to 1 second.
+    ///
+    /// This should be well under the request's own timeout, so that there is
+    /// time left to fall back.
+    #[must_use]
+    pub fn with_reply_timeout(mut self, reply_timeout: Duration) -> Self {
+        self.reply_timeout = reply_timeout;
+        self
+    }
+
+    #[must_use]
+    fn session(&self, session_url: &Url) -> Arc<SessionSocket> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .entry(session_url.clone())
+            .or_default()
+            .clone()
+    }
+}
+
+impl Default for ChromaWebSocketTransport {
+    fn default() -> Self {
+        Self {
+            fallback: Arc::new(ReqwestTransport::default()),
+            reconnect_delay: Duration::from_secs(5),
+            reply_timeout: Duration::from_secs(1),
+            sessions: Mutex::default(),
+        }
+    }
+}
+
+impl HttpTransport for ChromaWebSocketTransport {
+    fn send(&self, request: Request) -> TransportFuture {
+        let Some((session_url, endpoint)) = split_session_url(request.url()) else {
+            return self.fallback.send(request);
+        };
+
+        // Closing a session over REST also closes its socket
+        if request.method() == Method::DELETE && endpoint.is_empty() {
+            self.sessions.lock().unwrap().remove(&session_url);
+            return self.fallback.send(request);
+        }
+
+        if !is_effect_frame(request.method(), &endpoint) {
+            return self.fallback.send(request);
+        }
+
+        let session = self.session(&session_url);
+        if !session.is_available() {
+            return self.fallback.send(request);
+        }
+
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|bytes| serde_json::from_slice(bytes).ok())
+            .unwrap_or(Value::Null);
+        let frame = json!({ "endpoint": endpoint, "body": body }).to_string();
+
+        let fallback = self.fallback.clone();
+        let reconnect_delay = self.reconnect_delay;
+        let reply_timeout = self.reply_timeout;
+
+        Box::pin(async move {
+            match session.send_frame(&session_url, frame, reply_timeout).await {
+                Ok(reply) => Ok(HttpResponse::new(StatusCode::OK, reply.into())),
+                Err(()) => {
+                    session.back_off(reconnect_delay);
+                    fallback.send(request).await
+                }
+            }
+        })
+    }
+}
+
+#[derive(Default)]
+struct SessionSocket {
+    socket: tokio::sync::Mutex<Option<Socket>>,
+    retry_after: Mutex<Option<Instant>>,
+}
+
+impl SessionSocket {
+    #[must_use]
+    fn is_available(&self) -> bool {
+        self.retry_after
+            .lock()
+            .unwrap()
+            .map_or(true, |retry_after| Instant::now() >= retry_after)
+    }
+
+    fn back_off(&self, reconnect_delay: Duration) {
+        *self.retry_after.lock().unwrap() = Some(Instant::now() + reconnect_delay);
+    }
+
+    /// Sends a frame and waits for its reply, opening the socket first if
+    /// needed. The socket is dropped if anything goes wrong.
+    async fn send_frame(
+        &self,
+        session_url: &Url,
+        frame: String,
+        reply_timeout: Duration,
+    ) -> Result<String, ()> {
+        // Frames on the same session are sent one at a time, so that each
+        // reply can be matched to its frame
+        let mut socket = self.socket.lock().await;
+
+        // A server that accepts the connection but never finishes the
+        // handshake would otherwise hold up the frame until the request
+        // itself times out, leaving no time to fall back
+        if socket.is_none() {
+            let connect = tokio_tungstenite::connect_async(websocket_url(session_url));
+            let (connected, _) = tokio::time::timeout(reply_timeout, connect)
+                .await
+                .map_err(|_| ())?
+                .map_err(|_| ())?;
+            *socket = Some(connected);
+        }
+
+        let connected = socket
+            .as_mut()
+            .expect("socket was opened above if it wasn't already");
+
+        let result = tokio::time::timeout(reply_timeout, async {
+            connected.send(Message::Text(frame)).await.map_err(|_| ())?;
+
+            loop {
+                match connected.next().await {
+                    Some(Ok(Message::Text(reply))) => return Ok(reply),
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => return Err(()),
+                    Some(Ok(_)) => {}
+                }
+            }
+        })
+        .await
+        .unwrap_or(Err(()));
+
+        if result.is_err() {
+            *socket = None;
+        }
+
+        result
+    }
+}
+
+/// Splits a request URL into the session URL and the endpoint under it.
+///
+/// The session URL is everything up to and including the `chromasdk` path
+/// segment, which both the Chroma SDK and its mock use.
+#[must_use]
+fn split_session_url(url: &Url) -> Option<(Url, String)> {
+    let segments: Vec<_> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    let index = segments.iter().position(|s| *s == "chromasdk")?;
+
+    let mut session_url = url.clone();
+    session_url.set_path(&segments[..=index].join("/"));
+    session_url.set_query(None);
+
+    Some((session_url, segments[index + 1..].join("/")))
+}
+
+#[must_use]
+fn is_effect_frame(method: &Method, endpoint: &str) -> bool {
+    *method == Method::PUT
+        && (endpoint == "effect"
+            || SupportedDevice::ALL
+                .iter()
+                .any(|device| device.get_api() == endpoint))
+}
+
+#[must_use]
+fn websocket_url(session_url: &Url) -> Url {
+    let mut url = session_url.clone();
+    let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+
+    url.set_scheme(scheme)
+        .expect("http and https can always be swapped for ws and wss");
+    url
+}
@@ -0,0 +1,975 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::{diagnostic::DiagnosticsStore, prelude::*};
+use bevy_mod_chroma_api::{
+    api::{Effect, MouseEffect, RzResult},
+    AllDevicesEffect, Author, BGRColor, Category, Chroma, ChromaBrightness, ChromaCalibration,
+    ChromaDeviceState, ChromaDevices, ChromaDiagnosticsPlugin, ChromaHealth, ChromaHealthChanged,
+    ChromaHeartbeatSettings, ChromaPlugin, ChromaReplay, ChromaReplayPlugin, ChromaRunner,
+    ChromaRunnerInitializationSettings, DeviceCalibration, DeviceStatus, DimOnPause, EffectHandle,
+    InitRequest, MissingDevicePolicy, SupportedDevice,
+};
+use bevy_mod_chroma_mock::{FailureRule, MockChromaServer, MockFailure};
+use bevy_mod_chroma_request_lib::{
+    read_recording, HttpRequestTransport, RecordingTransport, ReqwestTransport,
+};
+use serde_json::json;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn app(server: &MockChromaServer) -> App {
+    app_with_heartbeat(server, ChromaHeartbeatSettings::default())
+}
+
+fn app_with_heartbeat(server: &MockChromaServer, heartbeat: ChromaHeartbeatSettings) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(plugin(server, vec![SupportedDevice::Mouse]).with_heartbeat(heartbeat));
+    app
+}
+
+fn plugin(server: &MockChromaServer, device_supported: Vec<SupportedDevice>) -> ChromaPlugin {
+    // The plugin only accepts static init URLs, and each server has its own
+    let init_url: &'static str = Box::leak(server.init_url().into_boxed_str());
+
+    ChromaPlugin::new(ChromaRunnerInitializationSettings::new_with_init_url(
+        init_url,
+        InitRequest {
+            title: "bevy_mod_chroma tests",
+            description: "bevy_mod_chroma tests",
+            author: Author {
+                name: "bevy_mod_chroma",
+                contact: "https://github.com/datael/bevy_mod_chroma",
+            },
+            device_supported,
+            category: Category::Application,
+        },
+    ))
+}
+
+/// Updates the app until `condition` holds, returning whether it did before
+/// timing out.
+fn update_until(app: &mut App, condition: impl Fn(&mut App) -> bool) -> bool {
+    let start = Instant::now();
+
+    while start.elapsed() < TIMEOUT {
+        app.update();
+
+        if condition(app) {
+            return true;
+        }
+
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    false
+}
+
+#[derive(Resource)]
+struct RedEffect(EffectHandle);
+
+fn create_and_apply_red(mut commands: Commands, mut chroma: Chroma) {
+    let handle = chroma.create_effect(Effect::Mouse(MouseEffect::Static {
+        color: BGRColor::RED,
+    }));
+
+    chroma.apply_effect(&handle);
+    commands.insert_resource(RedEffect(handle));
+}
+
+#[test]
+fn initializes_session_and_sends_heartbeats() {
+    let server = MockChromaServer::start();
+    let mut app = app(&server);
+
+    assert!(update_until(&mut app, |app| app
+        .world
+        .contains_resource::<ChromaRunner>()));
+
+    let init_requests = server.requests_matching("POST", "/razer/chromasdk");
+    assert_eq!(init_requests.len(), 1);
+    assert_eq!(init_requests[0].body["title"], "bevy_mod_chroma tests");
+    assert_eq!(init_requests[0].body["device_supported"], json!(["mouse"]));
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/heartbeat")
+        .is_empty()));
+}
+
+#[test]
+fn health_tracks_heartbeat_ticks_and_failures() {
+    let server = MockChromaServer::start();
+    let mut app = app(&server);
+    app.insert_resource(ChromaHealth::default().with_failure_threshold(1));
+
+    assert!(update_until(&mut app, |app| app
+        .world
+        .resource::<ChromaHealth>()
+        .tick()
+        .is_some()));
+
+    let health = app.world.resource::<ChromaHealth>();
+    assert!(health.is_healthy());
+    assert!(health.latency().is_some());
+    assert_eq!(health.consecutive_failures(), 0);
+
+    server.inject_failure(FailureRule::new(MockFailure::Status(500)).path_suffix("/heartbeat"));
+
+    assert!(update_until(&mut app, |app| !app
+        .world
+        .resource::<ChromaHealth>()
+        .is_healthy()));
+
+    let events = app.world.resource::<Events<ChromaHealthChanged>>();
+    assert!(events
+        .iter_current_update_events()
+        .any(|event| !event.healthy));
+}
+
+/// Counts the heartbeats sent over `duration` while an effect is applied on
+/// every frame.
+fn heartbeats_while_applying(adaptive: bool, duration: Duration) -> usize {
+    let server = MockChromaServer::start();
+    let mut app = app_with_heartbeat(
+        &server,
+        ChromaHeartbeatSettings {
+            interval: Duration::from_millis(100),
+            adaptive,
+            ..default()
+        },
+    );
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+
+    app.add_systems(Update, |mut chroma: Chroma, red: Res<RedEffect>| {
+        chroma.apply_effect(&red.0);
+    });
+    server.clear_requests();
+
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    server.requests_matching("PUT", "/heartbeat").len()
+}
+
+#[test]
+fn adaptive_heartbeats_are_skipped_while_applying_effects() {
+    assert!(heartbeats_while_applying(false, Duration::from_millis(500)) > 0);
+    assert_eq!(
+        heartbeats_while_applying(true, Duration::from_millis(500)),
+        0
+    );
+}
+
+#[test]
+fn background_heartbeats_continue_without_updates() {
+    let server = MockChromaServer::start();
+    let mut app = app_with_heartbeat(
+        &server,
+        ChromaHeartbeatSettings {
+            interval: Duration::from_millis(50),
+            background: true,
+            ..default()
+        },
+    );
+
+    assert!(update_until(&mut app, |app| app
+        .world
+        .contains_resource::<ChromaRunner>()));
+    app.update();
+
+    // No frames run while the app is "stuck", but heartbeats keep going
+    server.clear_requests();
+    std::thread::sleep(Duration::from_millis(500));
+    assert!(server.requests_matching("PUT", "/heartbeat").len() >= 3);
+
+    app.update();
+    let health = app.world.resource::<ChromaHealth>();
+    assert!(health.tick().is_some_and(|tick| tick >= 3));
+    assert!(health.is_healthy());
+}
+
+#[test]
+fn background_heartbeats_stop_when_disabled() {
+    let server = MockChromaServer::start();
+    let mut app = app_with_heartbeat(
+        &server,
+        ChromaHeartbeatSettings {
+            interval: Duration::from_millis(50),
+            background: true,
+            ..default()
+        },
+    );
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/heartbeat")
+        .is_empty()));
+
+    app.world
+        .resource_mut::<ChromaHeartbeatSettings>()
+        .background = false;
+    app.update();
+
+    // Heartbeats are only sent from systems now, and no frames run
+    std::thread::sleep(Duration::from_millis(20));
+    server.clear_requests();
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(server.requests_matching("PUT", "/heartbeat").is_empty());
+}
+
+#[test]
+fn creates_and_applies_effects() {
+    let server = MockChromaServer::start();
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+
+    let create_requests = server.requests_matching("POST", "/mouse");
+    assert_eq!(create_requests.len(), 1);
+    assert_eq!(
+        create_requests[0].body,
+        json!({ "effect": "CHROMA_STATIC", "param": { "color": 0x0000ff } })
+    );
+
+    let apply_requests = server.requests_matching("PUT", "/effect");
+    let id = apply_requests[0].body["id"].as_str().unwrap();
+    assert_eq!(server.effect(id), Some(create_requests[0].body.clone()));
+}
+
+#[test]
+fn missing_devices_are_probed_and_redirected() {
+    let server = MockChromaServer::start();
+    server.inject_failure(
+        FailureRule::new(MockFailure::Result(1167))
+            .method("PUT")
+            .path_suffix("/keyboard"),
+    );
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins).add_plugins(
+        plugin(
+            &server,
+            vec![SupportedDevice::Mouse, SupportedDevice::Keyboard],
+        )
+        .with_missing_device_policy(MissingDevicePolicy::Redirect(SupportedDevice::Mouse)),
+    );
+
+    assert!(update_until(&mut app, |app| app
+        .world
+        .resource::<ChromaDevices>()
+        .is_probed()));
+
+    let devices = app.world.resource::<ChromaDevices>();
+    assert_eq!(
+        devices.status(SupportedDevice::Mouse),
+        DeviceStatus::Present
+    );
+    assert_eq!(
+        devices.status(SupportedDevice::Keyboard),
+        DeviceStatus::Missing(RzResult::DeviceNotConnected)
+    );
+
+    server.clear_requests();
+    app.add_systems(Update, |mut chroma: Chroma, mut applied: Local<bool>| {
+        if !*applied {
+            let handle = chroma.create_effect(Effect::new_static(
+                SupportedDevice::Keyboard,
+                BGRColor::BLUE,
+            ));
+            chroma.apply_effect(&handle);
+            *applied = true;
+        }
+    });
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/mouse")
+        .is_empty()));
+
+    assert_eq!(
+        server.requests_matching("PUT", "/mouse")[0].body,
+        json!({ "effect": "CHROMA_STATIC", "param": { "color": 0xff0000 } })
+    );
+    assert!(server.requests_matching("POST", "/keyboard").is_empty());
+
+    // The redirected effect is what the mouse now shows
+    assert!(update_until(&mut app, |app| app
+        .world
+        .resource::<ChromaDeviceState>()
+        .effect(SupportedDevice::Mouse)
+        .is_some()));
+    assert_eq!(
+        app.world
+            .resource::<ChromaDeviceState>()
+            .effect(SupportedDevice::Mouse),
+        Some(&Effect::new_static(SupportedDevice::Mouse, BGRColor::BLUE))
+    );
+}
+
+#[test]
+fn missing_devices_are_not_redirected_to_devices_that_are_not_present() {
+    let server = MockChromaServer::start();
+    server.inject_failure(
+        FailureRule::new(MockFailure::Result(1167))
+            .method("PUT")
+            .path_suffix("/keyboard"),
+    );
+
+    // The redirect target isn't in `device_supported`, so was never probed
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins).add_plugins(
+        plugin(
+            &server,
+            vec![SupportedDevice::Mouse, SupportedDevice::Keyboard],
+        )
+        .with_missing_device_policy(MissingDevicePolicy::Redirect(SupportedDevice::ChromaLink)),
+    );
+
+    assert!(update_until(&mut app, |app| app
+        .world
+        .resource::<ChromaDevices>()
+        .is_probed()));
+
+    server.clear_requests();
+    app.add_systems(Update, |mut chroma: Chroma, mut applied: Local<bool>| {
+        if !*applied {
+            let handle = chroma.create_effect(Effect::new_static(
+                SupportedDevice::Keyboard,
+                BGRColor::BLUE,
+            ));
+            chroma.apply_effect(&handle);
+            *applied = true;
+        }
+    });
+
+    for _ in 0..20 {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    assert!(server.requests_matching("PUT", "/chromalink").is_empty());
+    assert!(server.requests_matching("POST", "/keyboard").is_empty());
+    assert!(app
+        .world
+        .resource::<ChromaDeviceState>()
+        .effect(SupportedDevice::ChromaLink)
+        .is_none());
+}
+
+/// An app that lights the mouse and keyboard together, with the keyboard
+/// turning out to be missing. The group is created before devices are probed,
+/// and leaves out the chroma link so that it can be redirected to.
+fn apply_group_with_missing_keyboard(policy: MissingDevicePolicy) -> (MockChromaServer, App) {
+    let server = MockChromaServer::start();
+    for method in ["POST", "PUT"] {
+        server.inject_failure(
+            FailureRule::new(MockFailure::Result(1167))
+                .method(method)
+                .path_suffix("/keyboard"),
+        );
+    }
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, ChromaDiagnosticsPlugin))
+        .add_plugins(
+            plugin(
+                &server,
+                vec![
+                    SupportedDevice::Mouse,
+                    SupportedDevice::Keyboard,
+                    SupportedDevice::ChromaLink,
+                ],
+            )
+            .with_missing_device_policy(policy),
+        )
+        .add_systems(Startup, |mut chroma: Chroma| {
+            chroma.apply_to_all(AllDevicesEffect::from_fn(|device| match device {
+                SupportedDevice::ChromaLink => Effect::new_none(SupportedDevice::Mouse),
+                device => Effect::new_static(device, BGRColor::BLUE),
+            }));
+        });
+
+    (server, app)
+}
+
+#[test]
+fn missing_group_members_are_redirected() {
+    let (server, mut app) = apply_group_with_missing_keyboard(MissingDevicePolicy::Redirect(
+        SupportedDevice::ChromaLink,
+    ));
+
+    let static_blue = json!({ "effect": "CHROMA_STATIC", "param": { "color": 0xff0000 } });
+    assert!(update_until(&mut app, |_| server
+        .requests_matching("PUT", "/chromalink")
+        .iter()
+        .any(|request| request.body == static_blue)));
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+}
+
+#[test]
+fn missing_group_members_that_are_dropped_are_counted() {
+    let (server, mut app) = apply_group_with_missing_keyboard(MissingDevicePolicy::Drop);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+    assert!(update_until(&mut app, |app| app
+        .world
+        .resource::<DiagnosticsStore>()
+        .get(ChromaDiagnosticsPlugin::DROPPED_APPLIES)
+        .and_then(|diagnostic| diagnostic.value())
+        == Some(1.0)));
+}
+
+#[derive(Resource)]
+struct AllDevices(Option<EffectHandle>);
+
+#[test]
+fn all_devices_effects_are_applied_and_deleted_together() {
+    let server = MockChromaServer::start();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(plugin(
+            &server,
+            vec![SupportedDevice::Mouse, SupportedDevice::Keyboard],
+        ))
+        .add_systems(Startup, |mut commands: Commands, mut chroma: Chroma| {
+            let handle = chroma.apply_to_all(BGRColor::RED);
+            commands.insert_resource(AllDevices(Some(handle)));
+        });
+
+    assert!(update_until(&mut app, |_| server
+        .requests_matching("PUT", "/effect")
+        .len()
+        == 2));
+
+    let static_red = json!({ "effect": "CHROMA_STATIC", "param": { "color": 0x0000ff } });
+    for device in ["/keyboard", "/mouse"] {
+        let create_requests = server.requests_matching("POST", device);
+        assert_eq!(create_requests.len(), 1);
+        assert_eq!(create_requests[0].body, static_red);
+    }
+
+    // Effects are applied one id at a time, as the API only takes one
+    let mut ids: Vec<_> = server
+        .requests_matching("PUT", "/effect")
+        .into_iter()
+        .map(|request| {
+            assert_eq!(request.body.get("ids"), None);
+            request.body["id"].as_str().unwrap().to_owned()
+        })
+        .collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 2);
+
+    for device in [SupportedDevice::Keyboard, SupportedDevice::Mouse] {
+        assert!(update_until(&mut app, |app| app
+            .world
+            .resource::<ChromaDeviceState>()
+            .effect(device)
+            .is_some()));
+    }
+
+    app.add_systems(
+        Update,
+        |mut chroma: Chroma, mut all_devices: ResMut<AllDevices>| {
+            if let Some(handle) = all_devices.0.take() {
+                chroma.delete_effect(handle);
+            }
+        },
+    );
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("DELETE", "/effect")
+        .is_empty()));
+
+    let delete_requests = server.requests_matching("DELETE", "/effect");
+    assert_eq!(delete_requests.len(), 1);
+    let mut deleted_ids: Vec<_> = delete_requests[0].body["ids"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|id| id.as_str().unwrap().to_owned())
+        .collect();
+    deleted_ids.sort();
+    assert_eq!(deleted_ids, ids);
+    for id in &ids {
+        assert_eq!(server.effect(id), None);
+    }
+}
+
+#[test]
+fn effects_deleted_in_the_frame_they_are_created_are_deleted_from_the_server() {
+    let server = MockChromaServer::start();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins).add_plugins(plugin(
+        &server,
+        vec![SupportedDevice::Mouse, SupportedDevice::Keyboard],
+    ));
+
+    assert!(update_until(&mut app, |app| app
+        .world
+        .resource::<ChromaDevices>()
+        .is_probed()));
+
+    app.add_systems(Update, |mut chroma: Chroma, mut deleted: Local<bool>| {
+        if !*deleted {
+            let handle = chroma.apply_to_all(BGRColor::RED);
+            chroma.delete_effect(handle);
+            *deleted = true;
+        }
+    });
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("DELETE", "/effect")
+        .is_empty()));
+
+    let created = server.requests_matching("POST", "/keyboard").len()
+        + server.requests_matching("POST", "/mouse").len();
+    assert_eq!(created, 2);
+
+    // Every created effect is deleted, rather than being left on the server
+    let deleted: Vec<_> = server
+        .requests_matching("DELETE", "/effect")
+        .iter()
+        .flat_map(|request| match request.body["ids"].as_array() {
+            Some(ids) => ids.clone(),
+            None => vec![request.body["id"].clone()],
+        })
+        .map(|id| id.as_str().unwrap().to_owned())
+        .collect();
+    assert_eq!(deleted.len(), created);
+    for id in &deleted {
+        assert_eq!(server.effect(id), None);
+    }
+}
+
+#[test]
+fn brightness_changes_resend_the_applied_effect() {
+    let server = MockChromaServer::start();
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+
+    app.world.resource_mut::<ChromaBrightness>().set_global(0.5);
+
+    let dimmed_red = json!({
+        "effect": "CHROMA_STATIC",
+        "param": { "color": BGRColor::RED.scale(0.5).as_u32() },
+    });
+    assert!(update_until(&mut app, |_| server
+        .requests_matching("PUT", "/mouse")
+        .iter()
+        .any(|request| request.body == dimmed_red)));
+}
+
+#[test]
+fn calibration_changes_resend_the_applied_effect() {
+    let server = MockChromaServer::start();
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+
+    let calibration = DeviceCalibration::default().with_gains(0.5, 1.0, 1.0);
+    app.world
+        .resource_mut::<ChromaCalibration>()
+        .set(SupportedDevice::Mouse, calibration);
+
+    let calibrated_red = json!({
+        "effect": "CHROMA_STATIC",
+        "param": { "color": calibration.calibrate(BGRColor::RED).as_u32() },
+    });
+    assert!(update_until(&mut app, |_| server
+        .requests_matching("PUT", "/mouse")
+        .iter()
+        .any(|request| request.body == calibrated_red)));
+}
+
+#[test]
+fn dimming_on_pause_sends_a_bounded_number_of_requests() {
+    let server = MockChromaServer::start();
+    let mut app = app(&server);
+    app.insert_resource(ChromaBrightness::default().with_dim_on_pause(DimOnPause {
+        brightness: 0.25,
+        fade_duration: Duration::from_millis(500),
+        when_unfocused: false,
+    }))
+    .add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+
+    app.world
+        .resource_mut::<ChromaBrightness>()
+        .set_paused(true);
+    server.clear_requests();
+
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    // The fade passes through dozens of brightness steps, but the effect is
+    // only sent again every so often, and the last send is fully dimmed
+    let dimmed_red = json!({
+        "effect": "CHROMA_STATIC",
+        "param": { "color": BGRColor::RED.scale(0.25).as_u32() },
+    });
+    let resend_requests = server.requests_matching("PUT", "/mouse");
+    assert!((1..=8).contains(&resend_requests.len()));
+    assert_eq!(resend_requests.last().unwrap().body, dimmed_red);
+
+    // Applying the effect while dimmed sends it straight to the device,
+    // rather than deleting it and creating it again
+    server.clear_requests();
+    app.add_systems(
+        Update,
+        |mut chroma: Chroma, mut applied: Local<bool>, red: Res<RedEffect>| {
+            if !*applied {
+                chroma.apply_effect(&red.0);
+                *applied = true;
+            }
+        },
+    );
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/mouse")
+        .is_empty()));
+    assert_eq!(
+        server.requests_matching("PUT", "/mouse")[0].body,
+        dimmed_red
+    );
+    assert!(server.requests_matching("POST", "/mouse").is_empty());
+    assert!(server.requests_matching("DELETE", "/effect").is_empty());
+}
+
+#[test]
+fn device_state_holds_the_last_applied_effect() {
+    let server = MockChromaServer::start();
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |app| app
+        .world
+        .resource::<ChromaDeviceState>()
+        .grid(SupportedDevice::Mouse)
+        .is_some()));
+
+    let device_state = app.world.resource::<ChromaDeviceState>();
+    assert_eq!(
+        device_state.effect(SupportedDevice::Mouse),
+        Some(&Effect::Mouse(MouseEffect::Static {
+            color: BGRColor::RED
+        }))
+    );
+
+    let grid = device_state.grid(SupportedDevice::Mouse).unwrap();
+    assert_eq!(grid.rows(), 9);
+    assert_eq!(grid.columns(), 7);
+    assert!(grid
+        .iter_rows()
+        .flatten()
+        .all(|color| *color == BGRColor::RED));
+    assert!(device_state.grid(SupportedDevice::Keyboard).is_none());
+}
+
+#[test]
+fn device_state_ignores_effects_the_server_failed_to_apply() {
+    let server = MockChromaServer::start();
+    server.inject_failure(
+        FailureRule::new(MockFailure::Result(87))
+            .method("PUT")
+            .path_suffix("/effect"),
+    );
+
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+
+    // Give the response time to be gathered
+    for _ in 0..20 {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    assert!(app
+        .world
+        .resource::<ChromaDeviceState>()
+        .effect(SupportedDevice::Mouse)
+        .is_none());
+}
+
+#[test]
+fn brightness_changes_do_not_resend_effects_the_server_failed_to_apply() {
+    let server = MockChromaServer::start();
+    server.inject_failure(
+        FailureRule::new(MockFailure::Result(87))
+            .method("PUT")
+            .path_suffix("/effect"),
+    );
+
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+
+    // Give the response time to be gathered
+    for _ in 0..20 {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    app.world.resource_mut::<ChromaBrightness>().set_global(0.5);
+    for _ in 0..20 {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    let dimmed_red = json!({
+        "effect": "CHROMA_STATIC",
+        "param": { "color": BGRColor::RED.scale(0.5).as_u32() },
+    });
+    assert!(!server
+        .requests_matching("PUT", "/mouse")
+        .iter()
+        .any(|request| request.body == dimmed_red));
+}
+
+#[test]
+fn diagnostics_report_requests_and_effects() {
+    let server = MockChromaServer::start();
+    let mut app = app(&server);
+    app.add_plugins(ChromaDiagnosticsPlugin)
+        .add_systems(Startup, create_and_apply_red);
+
+    let diagnostic = |app: &App, id| {
+        app.world
+            .resource::<DiagnosticsStore>()
+            .get(id)
+            .and_then(|diagnostic| diagnostic.value())
+    };
+
+    assert!(update_until(&mut app, |app| diagnostic(
+        app,
+        ChromaDiagnosticsPlugin::EFFECTS_ALIVE
+    ) == Some(1.0)));
+    assert!(update_until(&mut app, |app| diagnostic(
+        app,
+        ChromaDiagnosticsPlugin::APPLY_FAILURE_RATE
+    )
+    .is_some()));
+
+    assert_eq!(
+        diagnostic(&app, ChromaDiagnosticsPlugin::CREATE_FAILURE_RATE),
+        Some(0.0)
+    );
+    assert_eq!(
+        diagnostic(&app, ChromaDiagnosticsPlugin::APPLY_FAILURE_RATE),
+        Some(0.0)
+    );
+    assert_eq!(
+        diagnostic(&app, ChromaDiagnosticsPlugin::DROPPED_APPLIES),
+        Some(0.0)
+    );
+    assert!(diagnostic(&app, ChromaDiagnosticsPlugin::ROUND_TRIP_TIME).is_some());
+    assert!(diagnostic(&app, ChromaDiagnosticsPlugin::REQUESTS_PER_SECOND).is_some());
+}
+
+#[test]
+fn failed_apply_does_not_stop_later_applies() {
+    let server = MockChromaServer::start();
+    server.inject_failure(
+        FailureRule::new(MockFailure::Status(500))
+            .method("PUT")
+            .path_suffix("/effect")
+            .times(1),
+    );
+
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+
+    app.add_systems(Update, |mut chroma: Chroma, red: Res<RedEffect>| {
+        chroma.apply_effect(&red.0);
+    });
+
+    assert!(update_until(&mut app, |_| server
+        .requests_matching("PUT", "/effect")
+        .len()
+        >= 3));
+}
+
+#[test]
+fn failed_creates_back_off_before_retrying() {
+    let server = MockChromaServer::start();
+    server.inject_failure(
+        FailureRule::new(MockFailure::Status(500))
+            .method("POST")
+            .path_suffix("/mouse"),
+    );
+
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("POST", "/mouse")
+        .is_empty()));
+
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    // Retrying every frame would have sent hundreds of requests by now
+    let create_requests = server.requests_matching("POST", "/mouse");
+    assert!((2..=3).contains(&create_requests.len()));
+}
+
+#[test]
+fn creates_rejected_by_the_server_are_retried() {
+    let server = MockChromaServer::start();
+    server.inject_failure(
+        FailureRule::new(MockFailure::Result(87))
+            .method("POST")
+            .path_suffix("/mouse")
+            .times(1),
+    );
+
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("PUT", "/effect")
+        .is_empty()));
+
+    // The rejected create has no id, so only the retried one is applied
+    assert_eq!(server.requests_matching("POST", "/mouse").len(), 2);
+    let apply_requests = server.requests_matching("PUT", "/effect");
+    let id = apply_requests[0].body["id"].as_str().unwrap();
+    assert!(server.effect(id).is_some());
+}
+
+#[test]
+fn failed_init_does_not_start_session() {
+    let server = MockChromaServer::start();
+    server.inject_failure(FailureRule::new(MockFailure::Status(500)).method("POST"));
+
+    let mut app = app(&server);
+    app.add_systems(Startup, create_and_apply_red);
+
+    assert!(update_until(&mut app, |_| !server
+        .requests_matching("POST", "/razer/chromasdk")
+        .is_empty()));
+
+    for _ in 0..20 {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    assert!(!app.world.contains_resource::<ChromaRunner>());
+    assert_eq!(
+        server.requests_matching("POST", "/razer/chromasdk").len(),
+        1
+    );
+    assert!(server.requests_matching("POST", "/mouse").is_empty());
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn replays_recording_with_rewritten_session_and_effect_ids() {
+    let recording = SharedBuffer::default();
+    {
+        let server = MockChromaServer::start();
+        let mut app = app(&server);
+        app.insert_resource(HttpRequestTransport::new(RecordingTransport::new(
+            ReqwestTransport::default(),
+            recording.clone(),
+        )))
+        .add_systems(Startup, create_and_apply_red);
+
+        assert!(update_until(&mut app, |_| {
+            let recording = recording.0.lock().unwrap();
+            read_recording(recording.as_slice())
+                .unwrap()
+                .iter()
+                .any(|exchange| exchange.method == "PUT" && exchange.path.ends_with("/effect"))
+        }));
+    }
+
+    let exchanges = read_recording(recording.0.lock().unwrap().as_slice()).unwrap();
+
+    // Replaying twice against the same server means the second replay is
+    // handed a different session and effect id than were recorded
+    let server = MockChromaServer::start();
+    for _ in 0..2 {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(ChromaReplayPlugin::new(
+                &server.init_url(),
+                exchanges.clone(),
+            ));
+
+        assert!(update_until(&mut app, |app| app
+            .world
+            .resource::<ChromaReplay>()
+            .is_finished()));
+        assert!(!app.world.resource::<ChromaReplay>().has_failed());
+    }
+
+    let apply_requests = server.requests_matching("PUT", "/effect");
+    assert_eq!(apply_requests.len(), 2);
+    assert!(apply_requests[1].path.starts_with("/sessions/2/"));
+
+    let id = apply_requests[1].body["id"].as_str().unwrap();
+    assert_ne!(Some(id), apply_requests[0].body["id"].as_str());
+    assert_eq!(
+        server.effect(id),
+        Some(json!({ "effect": "CHROMA_STATIC", "param": { "color": 0x0000ff } }))
+    );
+}
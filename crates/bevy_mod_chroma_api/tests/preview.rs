@@ -0,0 +1,78 @@
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_mod_chroma_api::{
+    api::{Effect, MouseEffect},
+    Author, BGRColor, Category, Chroma, ChromaPlugin, ChromaPreviewTransport,
+    ChromaRunnerInitializationSettings, InitRequest, SupportedDevice,
+};
+use bevy_mod_chroma_request_lib::HttpRequestTransport;
+
+/// A writer whose contents can still be read after it has been handed to the
+/// preview.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn create_and_apply_red(mut chroma: Chroma) {
+    let handle = chroma.create_effect(Effect::Mouse(MouseEffect::Static {
+        color: BGRColor::RED,
+    }));
+
+    chroma.apply_effect(&handle);
+}
+
+#[test]
+fn preview_draws_applied_effects_to_the_terminal() {
+    let buffer = SharedBuffer::default();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(ChromaPlugin::new(ChromaRunnerInitializationSettings::new(
+            InitRequest {
+                title: "bevy_mod_chroma tests",
+                description: "bevy_mod_chroma tests",
+                author: Author {
+                    name: "bevy_mod_chroma",
+                    contact: "https://github.com/datael/bevy_mod_chroma",
+                },
+                device_supported: vec![SupportedDevice::Mouse],
+                category: Category::Application,
+            },
+        )))
+        .insert_resource(HttpRequestTransport::new(
+            ChromaPreviewTransport::to_writer(buffer.clone()),
+        ))
+        .add_systems(Startup, create_and_apply_red);
+
+    const RED: &str = "\x1b[48;2;255;0;0m";
+
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(5) && !buffer.contents().contains(RED) {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    let contents = buffer.contents();
+    assert!(contents.contains("mouse"));
+    assert!(contents.contains(RED));
+}
@@ -0,0 +1,197 @@
+#![cfg(all(feature = "unstable-websocket", not(target_family = "wasm")))]
+
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use bevy::{ecs::system::SystemState, prelude::*};
+use bevy_mod_chroma_api::ChromaWebSocketTransport;
+use bevy_mod_chroma_request_lib::{
+    HttpRequestPlugin, HttpRequestTransport, HttpRequests, HttpResponse, InMemoryTransport,
+};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use tungstenite::Message;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A local stand-in for the Chroma SDK WebSocket endpoint, which records
+/// every frame it receives and replies with `RZRESULT_SUCCESS`.
+struct WebSocketStandIn {
+    address: SocketAddr,
+    frames: Arc<Mutex<Vec<Value>>>,
+}
+
+impl WebSocketStandIn {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let frames = Arc::new(Mutex::new(Vec::new()));
+
+        std::thread::spawn({
+            let frames = frames.clone();
+            move || {
+                for stream in listener.incoming() {
+                    let frames = frames.clone();
+                    std::thread::spawn(move || {
+                        let mut socket = tungstenite::accept(stream.unwrap()).unwrap();
+
+                        while let Ok(message) = socket.read() {
+                            if let Message::Text(text) = message {
+                                frames
+                                    .lock()
+                                    .unwrap()
+                                    .push(serde_json::from_str(&text).unwrap());
+
+                                let reply = json!({ "result": 0 }).to_string();
+                                if socket.send(Message::Text(reply)).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    });
+                }
+            }
+        });
+
+        Self { address, frames }
+    }
+
+    fn frames(&self) -> Vec<Value> {
+        self.frames.lock().unwrap().clone()
+    }
+}
+
+/// A REST fallback that counts the requests it answers.
+fn counting_fallback() -> (InMemoryTransport, Arc<AtomicUsize>) {
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let transport = InMemoryTransport::new({
+        let count = count.clone();
+        move |_| {
+            count.fetch_add(1, Ordering::SeqCst);
+
+            Ok(HttpResponse::new(
+                StatusCode::OK,
+                json!({ "result": 0, "id": "rest" }).to_string().into(),
+            ))
+        }
+    });
+
+    (transport, count)
+}
+
+fn app(transport: ChromaWebSocketTransport) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, HttpRequestPlugin::default()))
+        .insert_resource(HttpRequestTransport::new(transport));
+    app
+}
+
+/// Sends a request through the app and waits for its response body.
+fn send(app: &mut App, method: Method, url: &str, body: Value) -> Value {
+    let mut state = SystemState::<HttpRequests>::new(&mut app.world);
+    let mut requests = state.get_mut(&mut app.world);
+    let request = requests.client().request(method, url).json(&body);
+    let handle = requests.request(request);
+    state.apply(&mut app.world);
+
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        app.update();
+
+        let requests = state.get_mut(&mut app.world);
+        if let Some(result) = requests.get_response(&handle) {
+            return result.as_ref().unwrap().json().unwrap();
+        }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    panic!("timed out");
+}
+
+#[test]
+fn sends_effect_frames_over_websocket() {
+    let stand_in = WebSocketStandIn::start();
+    let (fallback, rest_requests) = counting_fallback();
+    let mut app = app(ChromaWebSocketTransport::default().with_fallback(fallback));
+
+    let session_url = format!("http://{}/sessions/1/chromasdk", stand_in.address);
+    let effect = json!({ "effect": "CHROMA_STATIC", "param": { "color": 255 } });
+
+    for _ in 0..3 {
+        let response = send(
+            &mut app,
+            Method::PUT,
+            &format!("{session_url}/mouse"),
+            effect.clone(),
+        );
+        assert_eq!(response, json!({ "result": 0 }));
+    }
+
+    // Creating an effect needs its id back, so goes over REST
+    let response = send(
+        &mut app,
+        Method::POST,
+        &format!("{session_url}/mouse"),
+        effect.clone(),
+    );
+    assert_eq!(response["id"], "rest");
+
+    let frames = stand_in.frames();
+    assert_eq!(frames.len(), 3);
+    assert!(frames
+        .iter()
+        .all(|frame| *frame == json!({ "endpoint": "mouse", "body": effect })));
+    assert_eq!(rest_requests.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn falls_back_to_rest_without_websocket() {
+    // Bind and immediately release a port, so that nothing is listening on it
+    let address = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let (fallback, rest_requests) = counting_fallback();
+    let mut app = app(ChromaWebSocketTransport::default().with_fallback(fallback));
+
+    let url = format!("http://{address}/sessions/1/chromasdk/effect");
+    for _ in 0..2 {
+        let response = send(&mut app, Method::PUT, &url, json!({ "id": "effect" }));
+        assert_eq!(response["result"], 0);
+    }
+
+    assert_eq!(rest_requests.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn falls_back_to_rest_when_websocket_never_opens() {
+    // Accept connections but never answer the handshake, like a hung server
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let mut streams = Vec::new();
+        for stream in listener.incoming() {
+            streams.push(stream);
+        }
+    });
+
+    let (fallback, rest_requests) = counting_fallback();
+    let mut app = app(ChromaWebSocketTransport::default()
+        .with_fallback(fallback)
+        .with_reply_timeout(Duration::from_millis(100)));
+
+    let url = format!("http://{address}/sessions/1/chromasdk/effect");
+    let response = send(&mut app, Method::PUT, &url, json!({ "id": "effect" }));
+
+    assert_eq!(response["result"], 0);
+    assert_eq!(rest_requests.load(Ordering::SeqCst), 1);
+}
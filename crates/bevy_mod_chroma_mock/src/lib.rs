@@ -0,0 +1,230 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use tiny_http::Server;
+
+mod server;
+
+/// A local, in-process stand-in for the Chroma REST server, for testing
+/// without Razer Synapse.
+///
+/// The server listens on a random port on `127.0.0.1` and implements the init,
+/// heartbeat, device effect, effect and session endpoints. Every request it
+/// receives is recorded, and failures and latency can be injected.
+///
+/// The server shuts down when dropped.
+pub struct MockChromaServer {
+    address: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockChromaServer {
+    /// Starts a new server.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no local port could be bound.
+    #[must_use]
+    pub fn start() -> Self {
+        let server = Arc::new(Server::http("127.0.0.1:0").expect("bind mock chroma server"));
+        let address = server
+            .server_addr()
+            .to_ip()
+            .expect("mock chroma server has an ip address");
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let thread = std::thread::spawn({
+            let server = server.clone();
+            let state = state.clone();
+            move || server::run(&server, address, &state)
+        });
+
+        Self {
+            address,
+            state,
+            server,
+            thread: Some(thread),
+        }
+    }
+
+    #[must_use]
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The URL to pass as the init URL of the Chroma plugin.
+    #[must_use]
+    pub fn init_url(&self) -> String {
+        format!("http://{}/razer/chromasdk", self.address)
+    }
+
+    /// Returns every request received so far, in the order they arrived.
+    #[must_use]
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Returns the requests received so far with the given method whose path
+    /// ends with `path_suffix`.
+    #[must_use]
+    pub fn requests_matching(&self, method: &str, path_suffix: &str) -> Vec<RecordedRequest> {
+        self.state()
+            .requests
+            .iter()
+            .filter(|request| request.method == method && request.path.ends_with(path_suffix))
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear_requests(&self) {
+        self.state().requests.clear();
+    }
+
+    /// Returns the body of each effect that has been created and not yet
+    /// deleted, by effect id.
+    #[must_use]
+    pub fn effect(&self, id: &str) -> Option<Value> {
+        self.state()
+            .effects
+            .get(id)
+            .map(|effect| effect.body.clone())
+    }
+
+    /// Delays every response by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state().latency = latency;
+    }
+
+    /// Adds a rule that makes matching requests fail. Rules are checked in
+    /// the order they were added.
+    pub fn inject_failure(&self, rule: FailureRule) {
+        self.state().failure_rules.push(rule);
+    }
+
+    pub fn clear_failures(&self) {
+        self.state().failure_rules.clear();
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl Drop for MockChromaServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A request received by [`MockChromaServer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub received_at: Instant,
+    pub method: String,
+    pub path: String,
+    /// The JSON body of the request, or [`Value::Null`] if there was none.
+    pub body: Value,
+}
+
+/// Makes requests to [`MockChromaServer`] fail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureRule {
+    method: Option<String>,
+    path_suffix: Option<String>,
+    remaining: Option<usize>,
+    failure: MockFailure,
+}
+
+impl FailureRule {
+    /// Creates a rule that fails every request.
+    #[must_use]
+    pub fn new(failure: MockFailure) -> Self {
+        Self {
+            method: None,
+            path_suffix: None,
+            remaining: None,
+            failure,
+        }
+    }
+
+    #[must_use]
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = Some(method.to_uppercase());
+        self
+    }
+
+    #[must_use]
+    pub fn path_suffix(mut self, path_suffix: &str) -> Self {
+        self.path_suffix = Some(path_suffix.into());
+        self
+    }
+
+    /// Only fails the next `times` matching requests.
+    #[must_use]
+    pub fn times(mut self, times: usize) -> Self {
+        self.remaining = Some(times);
+        self
+    }
+
+    #[must_use]
+    fn matches(&self, method: &str, path: &str) -> bool {
+        self.remaining != Some(0)
+            && self.method.as_deref().map_or(true, |m| m == method)
+            && self
+                .path_suffix
+                .as_deref()
+                .map_or(true, |suffix| path.ends_with(suffix))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockFailure {
+    /// Responds with the given HTTP status code and an empty body.
+    Status(u16),
+    /// Responds with `200 OK` and the given `RZRESULT` code in the body.
+    Result(i64),
+}
+
+#[derive(Default)]
+struct MockState {
+    requests: Vec<RecordedRequest>,
+    failure_rules: Vec<FailureRule>,
+    latency: Duration,
+    next_session_id: u32,
+    next_effect_id: u32,
+    sessions: Vec<u32>,
+    effects: HashMap<String, MockEffect>,
+    tick: u32,
+}
+
+impl MockState {
+    /// Finds the first failure rule matching a request, using it up.
+    fn take_failure(&mut self, method: &str, path: &str) -> Option<MockFailure> {
+        let rule = self
+            .failure_rules
+            .iter_mut()
+            .find(|rule| rule.matches(method, path))?;
+
+        if let Some(remaining) = rule.remaining.as_mut() {
+            *remaining -= 1;
+        }
+
+        Some(rule.failure.clone())
+    }
+}
+
+struct MockEffect {
+    body: Value,
+}
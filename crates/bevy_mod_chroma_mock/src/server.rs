@@ -0,0 +1,145 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use bevy_mod_chroma_api::{
+    api::RzResult,
+    stand_in::{self, SessionEndpoint, StandInRoute},
+};
+use serde_json::{json, Value};
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::{MockEffect, MockFailure, MockState, RecordedRequest};
+
+pub(crate) fn run(server: &Server, address: SocketAddr, state: &Arc<Mutex<MockState>>) {
+    // Each request is handled on its own thread so that injected latency
+    // doesn't hold up other requests
+    for request in server.incoming_requests() {
+        let state = state.clone();
+        std::thread::spawn(move || handle(request, address, &state));
+    }
+}
+
+fn handle(mut request: Request, address: SocketAddr, state: &Mutex<MockState>) {
+    let mut body = String::new();
+    let _ = request.as_reader().read_to_string(&mut body);
+    let body = serde_json::from_str(&body).unwrap_or(Value::Null);
+
+    let method = request.method().to_string().to_uppercase();
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_owned();
+
+    let (latency, (status, response_body)) = {
+        let mut state = state.lock().unwrap();
+
+        state.requests.push(RecordedRequest {
+            received_at: Instant::now(),
+            method: method.clone(),
+            path: path.clone(),
+            body: body.clone(),
+        });
+
+        let response = match state.take_failure(&method, &path) {
+            Some(MockFailure::Status(status)) => (status, Value::Null),
+            Some(MockFailure::Result(result)) => (200, json!({ "result": result })),
+            None => route(&mut state, address, &method, &path, &body),
+        };
+
+        (state.latency, response)
+    };
+
+    if !latency.is_zero() {
+        std::thread::sleep(latency);
+    }
+
+    let response_body = if response_body.is_null() {
+        String::new()
+    } else {
+        response_body.to_string()
+    };
+
+    let content_type = Header::from_bytes("Content-Type", "application/json")
+        .expect("header name and value are valid ASCII");
+
+    let _ = request.respond(
+        Response::from_string(response_body)
+            .with_status_code(status)
+            .with_header(content_type),
+    );
+}
+
+/// Answers a request the same way as `ChromaPreviewTransport`, as both route
+/// through [`stand_in`], but keeps effects as their raw JSON body so that tests
+/// can check exactly what was sent.
+fn route(
+    state: &mut MockState,
+    address: SocketAddr,
+    method: &str,
+    path: &str,
+    body: &Value,
+) -> (u16, Value) {
+    let (session_id, endpoint) = match stand_in::route(method, path, body) {
+        StandInRoute::Init => {
+            state.next_session_id += 1;
+            let session_id = state.next_session_id;
+            state.sessions.push(session_id);
+
+            let uri = format!("http://{address}{}", stand_in::session_path(session_id));
+            return (200, stand_in::init_response(session_id, &uri));
+        }
+        StandInRoute::Session {
+            session_id,
+            endpoint,
+        } => (session_id, endpoint),
+        StandInRoute::MethodNotAllowed => return (405, Value::Null),
+        StandInRoute::NotFound => return (404, Value::Null),
+    };
+
+    if !state.sessions.contains(&session_id) {
+        return (404, Value::Null);
+    }
+
+    let result = match endpoint {
+        SessionEndpoint::Close => {
+            state.sessions.retain(|id| *id != session_id);
+            RzResult::Success
+        }
+        SessionEndpoint::Heartbeat => {
+            state.tick += 1;
+            return (200, stand_in::heartbeat_response(state.tick));
+        }
+        SessionEndpoint::ApplyEffect(Some(id)) if state.effects.contains_key(id) => {
+            RzResult::Success
+        }
+        SessionEndpoint::ApplyEffect(Some(_)) => RzResult::NotFound,
+        SessionEndpoint::ApplyEffect(None) => RzResult::InvalidParameter,
+        SessionEndpoint::DeleteEffects(ids) => {
+            let mut result = RzResult::Success;
+            for id in ids {
+                if state.effects.remove(id).is_none() {
+                    result = RzResult::NotFound;
+                }
+            }
+
+            result
+        }
+        SessionEndpoint::CreateEffect(_) => {
+            state.next_effect_id += 1;
+            let id = stand_in::effect_id(state.next_effect_id.into());
+            state
+                .effects
+                .insert(id.clone(), MockEffect { body: body.clone() });
+
+            return (200, stand_in::create_effect_response(&id));
+        }
+        SessionEndpoint::SetEffect(_) => RzResult::Success,
+    };
+
+    (200, stand_in::result_response(result))
+}
@@ -1,6 +1,13 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
 use bevy::{
     ecs::{schedule::ScheduleLabel, system::SystemParam},
-    prelude::{Commands, Entity, Query, Res, SystemSet},
+    prelude::{Commands, Entity, Event, Query, Res, Resource, SystemSet},
 };
 use bytes::Bytes;
 use plugin::{HttpRequest, HttpRequestClient, HttpResponseReceived};
@@ -8,6 +15,19 @@ use reqwest::{Client, RequestBuilder, StatusCode};
 use serde::Deserialize;
 
 mod plugin;
+mod record;
+mod retry;
+mod transport;
+mod typed;
+
+pub use record::{read_recording, RecordedExchange, RecordedResponse, RecordingTransport};
+pub use retry::HttpRetryPolicy;
+
+pub use transport::{
+    spawn_io_task, HttpRequestTransport, HttpTransport, InMemoryTransport, ReqwestTransport,
+    TransportFuture,
+};
+pub use typed::TypedRequestHandle;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, ScheduleLabel)]
 pub struct ExecuteHttpRequests;
@@ -21,12 +41,174 @@ pub enum HttpRequestSet {
     AfterGatherResponses,
 }
 
-pub struct HttpRequestPlugin;
+#[derive(Debug, Clone)]
+pub struct HttpRequestPlugin {
+    /// The timeout for requests that don't set their own. Defaults to 10
+    /// seconds.
+    pub default_timeout: Option<Duration>,
+    /// The most requests that may be in flight at once, or `None` for no
+    /// limit. Further requests wait in a queue. Defaults to 8.
+    pub max_in_flight: Option<usize>,
+    /// Whether to send an [`HttpResponseEvent`] for every response, so that
+    /// responses can be read with an `EventReader` instead of polling each
+    /// handle. Defaults to `false`.
+    pub emit_events: bool,
+    /// How idempotent requests that don't set their own policy are retried.
+    pub retry_policy: HttpRetryPolicy,
+}
+
+impl Default for HttpRequestPlugin {
+    fn default() -> Self {
+        Self {
+            default_timeout: Some(Duration::from_secs(10)),
+            max_in_flight: Some(8),
+            emit_events: false,
+            retry_policy: HttpRetryPolicy::default(),
+        }
+    }
+}
+
+/// Sent when a response arrives, if [`HttpRequestPlugin::emit_events`] is
+/// enabled.
+///
+/// The request still has to be disposed of, with [`HttpRequests::dispose`] or,
+/// when only the event is at hand, [`HttpRequests::dispose_by_id`].
+#[derive(Event, Debug, Clone)]
+pub struct HttpResponseEvent {
+    /// Matches the [`HttpRequestHandle::id`] of the request.
+    pub handle_id: HttpRequestId,
+    pub result: Result<HttpResponse, HttpRequestError>,
+}
+
+/// Settings for requests made through [`HttpRequests`], initialized from
+/// [`HttpRequestPlugin`].
+#[derive(Resource, Debug, Clone)]
+pub struct HttpRequestSettings {
+    pub default_timeout: Option<Duration>,
+    pub max_in_flight: Option<usize>,
+    pub retry_policy: HttpRetryPolicy,
+}
+
+/// Running totals of the requests made through [`HttpRequests`], for
+/// diagnostics. Retries count as separate requests, and requests sent directly
+/// with [`HttpRequestTransport::send`] aren't counted.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct HttpRequestStats {
+    pub(crate) sent: u64,
+    pub(crate) completed: u64,
+    pub(crate) failed: u64,
+    pub(crate) total_round_trip: Duration,
+    pub(crate) in_flight: usize,
+    pub(crate) queued: usize,
+}
+
+impl HttpRequestStats {
+    /// How many requests have been sent.
+    #[must_use]
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    /// How many sent requests have had a response or failed.
+    #[must_use]
+    pub fn completed(&self) -> u64 {
+        self.completed
+    }
+
+    /// How many completed requests failed, including timeouts.
+    #[must_use]
+    pub fn failed(&self) -> u64 {
+        self.failed
+    }
+
+    /// The sum of the round-trip times of all completed requests.
+    #[must_use]
+    pub fn total_round_trip(&self) -> Duration {
+        self.total_round_trip
+    }
+
+    /// How many requests were in flight after requests were last executed.
+    #[must_use]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// How many requests were waiting in the queue after requests were last
+    /// executed.
+    #[must_use]
+    pub fn queued(&self) -> usize {
+        self.queued
+    }
+}
+
+/// Which queued requests are sent first. Requests with the same priority are
+/// sent in the order they were made.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HttpRequestPriority {
+    Low,
+    #[default]
+    Normal,
+    /// Sent straight away without counting towards
+    /// [`HttpRequestSettings::max_in_flight`]. Meant for small, time-critical
+    /// requests like heartbeats.
+    High,
+}
+
+/// Options for a single request made with [`HttpRequests::request_with_options`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HttpRequestOptions {
+    timeout: Option<Option<Duration>>,
+    priority: HttpRequestPriority,
+    idempotent: bool,
+    retry_policy: Option<HttpRetryPolicy>,
+}
+
+impl HttpRequestOptions {
+    /// Fails the request with [`HttpRequestError::Timeout`] if no response
+    /// has arrived `timeout` after it was sent.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(Some(timeout));
+        self
+    }
+
+    /// Waits for the response for as long as it takes, ignoring
+    /// [`HttpRequestSettings::default_timeout`].
+    #[must_use]
+    pub fn without_timeout(mut self) -> Self {
+        self.timeout = Some(None);
+        self
+    }
+
+    #[must_use]
+    pub fn with_priority(mut self, priority: HttpRequestPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Marks the request as safe to send more than once, so that it is
+    /// retried after connection errors and 5xx responses using
+    /// [`HttpRequestSettings::retry_policy`].
+    #[must_use]
+    pub fn idempotent(mut self) -> Self {
+        self.idempotent = true;
+        self
+    }
+
+    /// Marks the request as idempotent, retrying it with `retry_policy`.
+    #[must_use]
+    pub fn with_retry_policy(mut self, retry_policy: HttpRetryPolicy) -> Self {
+        self.idempotent = true;
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+}
 
 #[derive(SystemParam)]
 pub struct HttpRequests<'w, 's> {
     commands: Commands<'w, 's>,
     client: Res<'w, HttpRequestClient>,
+    settings: Res<'w, HttpRequestSettings>,
     response_received_query: Query<'w, 's, &'static HttpResponseReceived>,
 }
 
@@ -38,8 +220,32 @@ impl<'w, 's> HttpRequests<'w, 's> {
 
     #[must_use]
     pub fn request(&mut self, request: RequestBuilder) -> HttpRequestHandle {
+        self.request_with_options(request, HttpRequestOptions::default())
+    }
+
+    #[must_use]
+    pub fn request_with_options(
+        &mut self,
+        request: RequestBuilder,
+        options: HttpRequestOptions,
+    ) -> HttpRequestHandle {
+        let timeout = options.timeout.unwrap_or(self.settings.default_timeout);
+        let retry_policy = options
+            .idempotent
+            .then(|| options.retry_policy.unwrap_or(self.settings.retry_policy));
+        let sequence = self.client.next_sequence.fetch_add(1, Ordering::Relaxed);
+
         HttpRequestHandle {
-            entity: self.commands.spawn(HttpRequest::new(request)).id(),
+            entity: self
+                .commands
+                .spawn(HttpRequest::new(
+                    request,
+                    timeout,
+                    options.priority,
+                    sequence,
+                    retry_policy,
+                ))
+                .id(),
         }
     }
 
@@ -58,6 +264,15 @@ impl<'w, 's> HttpRequests<'w, 's> {
     pub fn dispose(&mut self, handle: HttpRequestHandle) {
         self.commands.entity(handle.entity).despawn();
     }
+
+    /// Disposes of a request given only its id, such as one read from an
+    /// [`HttpResponseEvent`]. Does nothing if the request was already
+    /// disposed of.
+    pub fn dispose_by_id(&mut self, id: HttpRequestId) {
+        if let Some(mut entity) = self.commands.get_entity(id.0) {
+            entity.despawn();
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -65,13 +280,36 @@ pub struct HttpRequestHandle {
     entity: Entity,
 }
 
-#[derive(Debug)]
+impl HttpRequestHandle {
+    #[must_use]
+    pub fn id(&self) -> HttpRequestId {
+        HttpRequestId(self.entity)
+    }
+}
+
+/// Identifies a request, for matching it to an [`HttpResponseEvent`].
+///
+/// Unlike [`HttpRequestHandle`], ids can be freely copied, but can't be used
+/// to get the response. Requests can be disposed of by id with
+/// [`HttpRequests::dispose_by_id`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct HttpRequestId(Entity);
+
+#[derive(Debug, Clone)]
 pub struct HttpResponse {
     body_bytes: Bytes,
     status_code: StatusCode,
 }
 
 impl HttpResponse {
+    #[must_use]
+    pub fn new(status_code: StatusCode, body_bytes: Bytes) -> Self {
+        Self {
+            body_bytes,
+            status_code,
+        }
+    }
+
     #[must_use]
     pub fn body_bytes(&self) -> &Bytes {
         &self.body_bytes
@@ -90,13 +328,79 @@ impl HttpResponse {
     }
 }
 
+/// A source error kept by [`HttpRequestError`], shared so that errors can be
+/// cloned.
+pub type HttpRequestErrorSource = Arc<dyn Error + Send + Sync>;
+
 #[derive(Debug, Clone)]
 pub enum HttpRequestError {
-    RequestFailed(String),
+    /// The server couldn't be reached, or the connection failed.
+    Connect(HttpRequestErrorSource),
+    /// No response arrived before the request's timeout.
+    Timeout,
+    /// The server answered with a status other than 2xx.
+    Status(StatusCode, Bytes),
+    /// The response body couldn't be read or decoded.
+    Decode(HttpRequestErrorSource),
+    /// The request stopped before it had a response.
+    Cancelled,
+    /// The request couldn't be built, for example because its URL or body
+    /// was invalid.
+    Builder(HttpRequestErrorSource),
+    /// An idempotent request was retried until its retry policy gave up.
+    RetriesExhausted {
+        attempts: u32,
+        last_error: Box<HttpRequestError>,
+    },
+}
+
+impl Display for HttpRequestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(error) => write!(f, "connection failed: {error}"),
+            Self::Timeout => write!(f, "timed out"),
+            Self::Status(status_code, body) => write!(
+                f,
+                "server responded with {status_code}: {}",
+                String::from_utf8_lossy(body)
+            ),
+            Self::Decode(error) => write!(f, "failed to decode response: {error}"),
+            Self::Cancelled => write!(f, "cancelled"),
+            Self::Builder(error) => write!(f, "failed to build request: {error}"),
+            Self::RetriesExhausted {
+                attempts,
+                last_error,
+            } => write!(f, "gave up after {attempts} attempts: {last_error}"),
+        }
+    }
+}
+
+impl Error for HttpRequestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Connect(error) | Self::Decode(error) | Self::Builder(error) => Some(&**error),
+            Self::RetriesExhausted { last_error, .. } => Some(last_error),
+            Self::Timeout | Self::Status(..) | Self::Cancelled => None,
+        }
+    }
 }
 
 impl From<reqwest::Error> for HttpRequestError {
     fn from(error: reqwest::Error) -> Self {
-        Self::RequestFailed(format!("{error:?}"))
+        if error.is_timeout() {
+            Self::Timeout
+        } else if error.is_builder() {
+            Self::Builder(Arc::new(error))
+        } else if error.is_decode() || error.is_body() {
+            Self::Decode(Arc::new(error))
+        } else {
+            Self::Connect(Arc::new(error))
+        }
+    }
+}
+
+impl From<serde_json::Error> for HttpRequestError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Decode(Arc::new(error))
     }
 }
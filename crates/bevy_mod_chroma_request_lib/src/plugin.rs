@@ -1,19 +1,27 @@
-#[cfg(not(target_family = "wasm"))]
-use async_compat::Compat;
+use std::{cmp::Reverse, sync::atomic::AtomicU64, time::Duration};
 
 use bevy::{
     app::MainScheduleOrder,
+    log::info_span,
     prelude::{
-        App, Commands, Component, Entity, IntoSystemConfigs, IntoSystemSetConfigs, Plugin,
-        PostUpdate, Query, Resource, Without,
+        App, Commands, Component, Entity, Events, IntoSystemConfigs, IntoSystemSetConfigs, Plugin,
+        PostUpdate, Query, Res, ResMut, Resource, Without,
+    },
+    utils::{
+        tracing::{field, Instrument, Span},
+        Instant,
     },
-    tasks::IoTaskPool,
 };
-use crossbeam_channel::Receiver;
-use reqwest::{Client, RequestBuilder};
+use crossbeam_channel::{Receiver, TryRecvError};
+use futures_util::future::{AbortHandle, Abortable};
+use reqwest::{Client, Request, RequestBuilder};
 
 use crate::{
-    ExecuteHttpRequests, HttpRequestError, HttpRequestPlugin, HttpRequestSet, HttpResponse,
+    transport::{spawn_io_task, HttpRequestTransport, TransportFuture},
+    typed::{system_dispose_dropped_requests, system_run_callbacks, DroppedRequests},
+    ExecuteHttpRequests, HttpRequestError, HttpRequestId, HttpRequestPlugin, HttpRequestPriority,
+    HttpRequestSet, HttpRequestSettings, HttpRequestStats, HttpResponse, HttpResponseEvent,
+    HttpRetryPolicy,
 };
 
 impl Plugin for HttpRequestPlugin {
@@ -39,34 +47,130 @@ impl Plugin for HttpRequestPlugin {
             (
                 system_execute_requests.in_set(HttpRequestSet::ExecuteRequests),
                 system_gather_responses.in_set(HttpRequestSet::GatherResponses),
+                system_run_callbacks
+                    .after(system_gather_responses)
+                    .in_set(HttpRequestSet::GatherResponses),
+                system_dispose_dropped_requests.in_set(HttpRequestSet::AfterGatherResponses),
             ),
         )
-        .init_resource::<HttpRequestClient>();
+        .insert_resource(HttpRequestSettings {
+            default_timeout: self.default_timeout,
+            max_in_flight: self.max_in_flight,
+            retry_policy: self.retry_policy,
+        })
+        .init_resource::<HttpRequestClient>()
+        .init_resource::<HttpRequestTransport>()
+        .init_resource::<HttpRequestStats>();
+
+        if self.emit_events {
+            app.add_event::<HttpResponseEvent>();
+        }
     }
 }
 
 #[derive(Resource, Default)]
 pub(crate) struct HttpRequestClient {
     pub(crate) client: Client,
+    /// Numbers requests in the order they were made, so that the queue can
+    /// be kept in FIFO order
+    pub(crate) next_sequence: AtomicU64,
+    pub(crate) dropped_requests: DroppedRequests,
 }
 
-#[derive(Component)]
+#[derive(Component, Debug)]
 pub(crate) struct HttpRequest {
-    builder: Option<RequestBuilder>,
+    request: Option<PendingRequest>,
+    timeout: Option<Duration>,
+    priority: HttpRequestPriority,
+    sequence: u64,
+    retry_policy: Option<HttpRetryPolicy>,
+    /// How many times the request has already been sent
+    attempts: u32,
+    /// Retries aren't sent again until their backoff has passed
+    not_before: Option<Instant>,
+    /// Covers the whole lifecycle of the request, from being made until its
+    /// response is gathered
+    span: Span,
+}
+
+#[derive(Debug)]
+enum PendingRequest {
+    Builder(RequestBuilder),
+    /// A copy of a request that has already been sent, kept for retrying
+    Built(Request),
 }
 
 impl HttpRequest {
     #[must_use]
-    pub(crate) fn new(builder: RequestBuilder) -> Self {
+    pub(crate) fn new(
+        builder: RequestBuilder,
+        timeout: Option<Duration>,
+        priority: HttpRequestPriority,
+        sequence: u64,
+        retry_policy: Option<HttpRetryPolicy>,
+    ) -> Self {
         Self {
-            builder: Some(builder),
+            request: Some(PendingRequest::Builder(builder)),
+            timeout,
+            priority,
+            sequence,
+            retry_policy,
+            attempts: 0,
+            not_before: None,
+            span: info_span!(
+                "http_request",
+                method = field::Empty,
+                url = field::Empty,
+                status = field::Empty,
+                error = field::Empty,
+            ),
+        }
+    }
+
+    /// Prepares the next attempt at sending `request`, if the retry policy
+    /// allows for one
+    #[must_use]
+    fn next_attempt(&self, request: &Request) -> Option<Self> {
+        let retry_policy = self.retry_policy?;
+        if self.attempts + 1 >= retry_policy.max_attempts {
+            return None;
         }
+
+        Some(Self {
+            request: Some(PendingRequest::Built(request.try_clone()?)),
+            timeout: self.timeout,
+            priority: self.priority,
+            sequence: self.sequence,
+            retry_policy: self.retry_policy,
+            attempts: self.attempts + 1,
+            not_before: None,
+            span: self.span.clone(),
+        })
     }
 }
 
 #[derive(Component, Debug)]
 pub(crate) struct HttpRequestInProgress {
     pub receiver: Receiver<Result<HttpResponse, HttpRequestError>>,
+    /// Checked here rather than by the transport, as reqwest's own timeouts
+    /// aren't supported on wasm
+    pub deadline: Option<Instant>,
+    priority: HttpRequestPriority,
+    abort_handle: AbortHandle,
+    sent_at: Instant,
+    span: Span,
+    /// Which attempt at sending the request this is, starting from 1
+    attempt: u32,
+    next_attempt: Option<HttpRequest>,
+}
+
+impl Drop for HttpRequestInProgress {
+    /// Aborts the request when it is disposed of or times out, so that
+    /// abandoned requests don't keep running in the background. Once the
+    /// response has arrived this does nothing.
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
 }
 
 #[derive(Component, Debug)]
@@ -76,57 +180,186 @@ pub(crate) struct HttpResponseReceived {
 
 fn system_execute_requests(
     mut commands: Commands,
+    settings: Res<HttpRequestSettings>,
+    transport: Res<HttpRequestTransport>,
+    mut stats: ResMut<HttpRequestStats>,
     mut query: Query<(Entity, &mut HttpRequest), Without<HttpRequestInProgress>>,
+    in_progress_query: Query<&HttpRequestInProgress>,
 ) {
-    for (entity, mut request) in query.iter_mut() {
-        let (sender, receiver) = crossbeam_channel::bounded(1);
+    let now = Instant::now();
+    let mut queue: Vec<_> = query
+        .iter_mut()
+        .filter(|(_, request)| {
+            request
+                .not_before
+                .map_or(true, |not_before| now >= not_before)
+        })
+        .collect();
+    queue.sort_by_key(|(_, request)| (Reverse(request.priority), request.sequence));
+
+    let mut available = settings.max_in_flight.map_or(usize::MAX, |max| {
+        max.saturating_sub(
+            in_progress_query
+                .iter()
+                .filter(|in_progress| in_progress.priority != HttpRequestPriority::High)
+                .count(),
+        )
+    });
 
-        // SAFETY: This system is only invoked when a request builder is not
-        // already in progress, and we only allow construction of a request by
-        // passing in a request builder instance, so this will never be None.
-        let request_builder = request.builder.take().unwrap();
-
-        let task = async move {
-            async fn run_request(
-                request: RequestBuilder,
-            ) -> Result<HttpResponse, HttpRequestError> {
-                let response = request.send().await?;
-                let status_code = response.status();
-
-                Ok(HttpResponse {
-                    status_code,
-                    body_bytes: response.bytes().await?,
-                })
+    stats.in_flight = in_progress_query.iter().count();
+    stats.queued = queue.len();
+
+    for (entity, mut request) in queue {
+        if request.priority != HttpRequestPriority::High {
+            if available == 0 {
+                break;
             }
 
-            let result = run_request(request_builder).await;
-            sender.send(result).expect("sent successfully");
+            available -= 1;
+        }
+
+        stats.sent += 1;
+        stats.in_flight += 1;
+        stats.queued -= 1;
+
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+
+        // This system is only invoked when a request is not already in
+        // progress, and requests can only be constructed with a request
+        let built = match request
+            .request
+            .take()
+            .expect("request is only taken once it is sent")
+        {
+            PendingRequest::Builder(builder) => builder.build(),
+            PendingRequest::Built(built) => Ok(built),
         };
 
-        // For non-wasm targets, packing inside a compatability adapter is
-        // sufficient to prevent the Tokio runtime error that occurs without.
-        #[cfg(not(target_family = "wasm"))]
-        let task = Compat::new(task);
+        let mut next_attempt = None;
+        let response: TransportFuture = match built {
+            Ok(built) => {
+                request.span.record("method", built.method().as_str());
+                request.span.record("url", built.url().as_str());
+                next_attempt = request.next_attempt(&built);
+                transport.send(built)
+            }
+            Err(error) => Box::pin(async move { Err(error.into()) }),
+        };
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let response = Abortable::new(response, abort_registration);
 
-        IoTaskPool::get().spawn(task).detach();
+        let attempt = request.attempts + 1;
+        spawn_io_task(
+            async move {
+                if let Ok(result) = response.await {
+                    // The receiver may already be gone if the request was
+                    // disposed of while the response was being sent
+                    let _ = sender.send(result);
+                }
+            }
+            .instrument(info_span!(parent: &request.span, "http_request_send", attempt)),
+        )
+        .detach();
 
         commands
             .entity(entity)
-            .insert(HttpRequestInProgress { receiver })
+            .insert(HttpRequestInProgress {
+                receiver,
+                deadline: request.timeout.map(|timeout| Instant::now() + timeout),
+                abort_handle,
+                priority: request.priority,
+                sent_at: Instant::now(),
+                span: request.span.clone(),
+                attempt,
+                next_attempt,
+            })
             .remove::<HttpRequest>();
     }
 }
 
 fn system_gather_responses(
     mut commands: Commands,
-    query: Query<(Entity, &HttpRequestInProgress), Without<HttpResponseReceived>>,
+    mut stats: ResMut<HttpRequestStats>,
+    mut query: Query<(Entity, &mut HttpRequestInProgress), Without<HttpResponseReceived>>,
+    mut events: Option<ResMut<Events<HttpResponseEvent>>>,
 ) {
-    for (entity, in_progress) in query.iter() {
-        if let Ok(result) = in_progress.receiver.try_recv() {
-            commands
-                .entity(entity)
-                .insert(HttpResponseReceived { result })
-                .remove::<HttpRequestInProgress>();
+    for (entity, mut in_progress) in query.iter_mut() {
+        let result = match in_progress.receiver.try_recv() {
+            Ok(result) => result,
+            // The task was dropped without sending a response, which happens
+            // when the task pool shuts down
+            Err(TryRecvError::Disconnected) => Err(HttpRequestError::Cancelled),
+            Err(TryRecvError::Empty)
+                if in_progress
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline) =>
+            {
+                Err(HttpRequestError::Timeout)
+            }
+            Err(TryRecvError::Empty) => continue,
+        };
+
+        let _span = info_span!(parent: &in_progress.span, "http_request_response").entered();
+
+        stats.completed += 1;
+        stats.total_round_trip += in_progress.sent_at.elapsed();
+        if result.is_err() {
+            stats.failed += 1;
         }
+
+        let result = match result {
+            Err(error) if error.is_retryable() => {
+                if let Some(mut next_attempt) = in_progress.next_attempt.take() {
+                    let retry_policy = next_attempt
+                        .retry_policy
+                        .expect("there is only a next attempt if there is a policy");
+                    next_attempt.not_before =
+                        Some(Instant::now() + retry_policy.backoff(in_progress.attempt));
+
+                    commands
+                        .entity(entity)
+                        .insert(next_attempt)
+                        .remove::<HttpRequestInProgress>();
+                    continue;
+                }
+
+                if in_progress.attempt > 1 {
+                    Err(HttpRequestError::RetriesExhausted {
+                        attempts: in_progress.attempt,
+                        last_error: Box::new(error),
+                    })
+                } else {
+                    Err(error)
+                }
+            }
+            result => result,
+        };
+
+        match &result {
+            Ok(response) => {
+                in_progress
+                    .span
+                    .record("status", response.status_code().as_u16());
+            }
+            Err(error) => {
+                if let HttpRequestError::Status(status_code, _) = error {
+                    in_progress.span.record("status", status_code.as_u16());
+                }
+                in_progress.span.record("error", field::display(error));
+            }
+        }
+
+        if let Some(events) = events.as_mut() {
+            events.send(HttpResponseEvent {
+                handle_id: HttpRequestId(entity),
+                result: result.clone(),
+            });
+        }
+
+        commands
+            .entity(entity)
+            .insert(HttpResponseReceived { result })
+            .remove::<HttpRequestInProgress>();
     }
 }
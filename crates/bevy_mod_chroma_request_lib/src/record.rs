@@ -0,0 +1,161 @@
+use std::{
+    io::{self, BufRead, Write},
+    sync::{Arc, Mutex},
+};
+
+use bevy::utils::Instant;
+use reqwest::Request;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{HttpTransport, TransportFuture};
+
+/// One request sent through a [`RecordingTransport`], and its response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    /// Milliseconds from the start of the recording to when the request was
+    /// sent.
+    pub timestamp_ms: u64,
+    pub method: String,
+    pub path: String,
+    /// The JSON body of the request, or `null` if it had none.
+    pub body: Value,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedResponse {
+    Received {
+        status: u16,
+        /// The JSON body of the response, or `null` if it wasn't JSON.
+        body: Value,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// Wraps another transport, writing every exchange to `writer` as one line
+/// of JSON per [`RecordedExchange`].
+///
+/// Lines are written as responses arrive, so they may be slightly out of
+/// order by [`RecordedExchange::timestamp_ms`]. Requests that are disposed of,
+/// aborted or time out before their response arrives are recorded as
+/// [`RecordedResponse::Failed`] with a `cancelled` error.
+pub struct RecordingTransport<T> {
+    inner: T,
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    started_at: Instant,
+}
+
+impl<T: HttpTransport> RecordingTransport<T> {
+    #[must_use]
+    pub fn new(inner: T, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            inner,
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Records to a newly created file at `path`, replacing any existing file.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn to_file(inner: T, path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        let file = std::fs::File::create(path)?;
+
+        Ok(Self::new(inner, io::LineWriter::new(file)))
+    }
+}
+
+impl<T: HttpTransport> HttpTransport for RecordingTransport<T> {
+    fn send(&self, request: Request) -> TransportFuture {
+        let mut recorder = ExchangeRecorder {
+            writer: self.writer.clone(),
+            timestamp_ms: self.started_at.elapsed().as_millis() as u64,
+            method: request.method().to_string(),
+            path: request.url().path().to_owned(),
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .and_then(|bytes| serde_json::from_slice(bytes).ok())
+                .unwrap_or(Value::Null),
+            recorded: false,
+        };
+
+        let response = self.inner.send(request);
+
+        Box::pin(async move {
+            let result = response.await;
+
+            recorder.record(match &result {
+                Ok(response) => RecordedResponse::Received {
+                    status: response.status_code().as_u16(),
+                    body: response.json().unwrap_or(Value::Null),
+                },
+                Err(error) => RecordedResponse::Failed {
+                    error: error.to_string(),
+                },
+            });
+
+            result
+        })
+    }
+}
+
+/// Writes one exchange to the recording once its response arrives, or as
+/// cancelled if the request is dropped before then.
+struct ExchangeRecorder {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    timestamp_ms: u64,
+    method: String,
+    path: String,
+    body: Value,
+    recorded: bool,
+}
+
+impl ExchangeRecorder {
+    fn record(&mut self, response: RecordedResponse) {
+        self.recorded = true;
+
+        let exchange = RecordedExchange {
+            timestamp_ms: self.timestamp_ms,
+            method: std::mem::take(&mut self.method),
+            path: std::mem::take(&mut self.path),
+            body: self.body.take(),
+            response,
+        };
+
+        // Recording is best effort, and must never fail the request
+        if let Ok(line) = serde_json::to_string(&exchange) {
+            let mut writer = self.writer.lock().unwrap();
+            let _ = writeln!(writer, "{line}");
+        }
+    }
+}
+
+impl Drop for ExchangeRecorder {
+    /// Records requests that were disposed of, aborted or timed out before
+    /// their response arrived, which are otherwise dropped without a trace.
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.record(RecordedResponse::Failed {
+                error: "cancelled".to_owned(),
+            });
+        }
+    }
+}
+
+/// Reads a recording written by [`RecordingTransport`], sorted by
+/// [`RecordedExchange::timestamp_ms`].
+pub fn read_recording(reader: impl BufRead) -> io::Result<Vec<RecordedExchange>> {
+    let mut exchanges = reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| serde_json::from_str(&line?).map_err(io::Error::from))
+        .collect::<io::Result<Vec<RecordedExchange>>>()?;
+
+    exchanges.sort_by_key(|exchange| exchange.timestamp_ms);
+
+    Ok(exchanges)
+}
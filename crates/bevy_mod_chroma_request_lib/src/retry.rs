@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use crate::HttpRequestError;
+
+/// How requests marked with [`HttpRequestOptions::idempotent`](crate::HttpRequestOptions::idempotent)
+/// are retried after a connection error or a 5xx response.
+///
+/// Retries wait for an exponentially increasing backoff before being sent
+/// again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HttpRetryPolicy {
+    /// The most times a request is sent, including the first. Defaults to 3.
+    pub max_attempts: u32,
+    /// The backoff after the first attempt fails. Defaults to 100ms.
+    pub initial_backoff: Duration,
+    /// How much the backoff grows after each further attempt. Defaults to 2.
+    pub backoff_multiplier: f32,
+    /// Defaults to 2 seconds.
+    pub max_backoff: Duration,
+}
+
+impl Default for HttpRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            backoff_multiplier: 2.0,
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl HttpRetryPolicy {
+    /// The backoff to wait for after attempt number `attempt` (starting from
+    /// 1) fails.
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64()
+            * f64::from(self.backoff_multiplier.max(0.0)).powi(exponent);
+
+        Duration::from_secs_f64(backoff.clamp(0.0, self.max_backoff.as_secs_f64()))
+    }
+}
+
+impl HttpRequestError {
+    /// Whether a request that failed with this error might succeed if sent
+    /// again.
+    #[must_use]
+    pub(crate) fn is_retryable(&self) -> bool {
+        match self {
+            Self::Connect(_) => true,
+            Self::Status(status_code, _) => status_code.is_server_error(),
+            _ => false,
+        }
+    }
+}
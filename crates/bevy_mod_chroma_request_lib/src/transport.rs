@@ -0,0 +1,162 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+#[cfg(not(target_family = "wasm"))]
+use async_compat::Compat;
+
+use bevy::{
+    prelude::Resource,
+    tasks::{IoTaskPool, Task},
+};
+use reqwest::{Client, Request};
+
+use crate::{HttpRequestError, HttpResponse};
+
+/// The future returned by [`HttpTransport::send`].
+///
+/// Futures are only required to be `Send` on non-wasm targets, as the browser
+/// fetch API that reqwest uses on wasm is not `Send`.
+#[cfg(not(target_family = "wasm"))]
+pub type TransportFuture =
+    Pin<Box<dyn Future<Output = Result<HttpResponse, HttpRequestError>> + Send>>;
+
+/// The future returned by [`HttpTransport::send`].
+///
+/// Futures are only required to be `Send` on non-wasm targets, as the browser
+/// fetch API that reqwest uses on wasm is not `Send`.
+#[cfg(target_family = "wasm")]
+pub type TransportFuture = Pin<Box<dyn Future<Output = Result<HttpResponse, HttpRequestError>>>>;
+
+/// Sends requests and receives their responses on behalf of
+/// [`HttpRequestPlugin`](crate::HttpRequestPlugin).
+///
+/// Requests are still built with reqwest, via [`HttpRequests::client`](crate::HttpRequests::client),
+/// but the transport decides how they are actually sent. Futures returned by
+/// [`HttpTransport::send`] are run on the `IoTaskPool`.
+pub trait HttpTransport: Send + Sync + 'static {
+    fn send(&self, request: Request) -> TransportFuture;
+}
+
+/// The transport used by [`HttpRequestPlugin`](crate::HttpRequestPlugin).
+///
+/// Defaults to [`ReqwestTransport`]. Insert this resource to swap in a
+/// different transport.
+#[derive(Resource, Clone)]
+pub struct HttpRequestTransport {
+    pub(crate) transport: Arc<dyn HttpTransport>,
+}
+
+impl HttpRequestTransport {
+    #[must_use]
+    pub fn new(transport: impl HttpTransport) -> Self {
+        Self {
+            transport: Arc::new(transport),
+        }
+    }
+
+    #[must_use]
+    pub fn from_arc(transport: Arc<dyn HttpTransport>) -> Self {
+        Self { transport }
+    }
+
+    /// Sends a request directly, for background tasks that can't wait for
+    /// [`HttpRequests`](crate::HttpRequests). As with `HttpRequests`,
+    /// responses with a non-2xx status are errors, but requests sent this way
+    /// skip the queue, aren't retried or timed out, and aren't counted in
+    /// [`HttpRequestStats`](crate::HttpRequestStats).
+    ///
+    /// The future should be run with [`spawn_io_task`].
+    pub fn send(&self, request: Request) -> TransportFuture {
+        let response = self.transport.send(request);
+
+        Box::pin(async move {
+            let response = response.await?;
+
+            if response.status_code().is_success() {
+                Ok(response)
+            } else {
+                Err(HttpRequestError::Status(
+                    response.status_code(),
+                    response.body_bytes().clone(),
+                ))
+            }
+        })
+    }
+}
+
+/// Spawns a future on the `IoTaskPool`, in a context where the futures of
+/// every [`HttpTransport`] can run.
+#[cfg(not(target_family = "wasm"))]
+pub fn spawn_io_task<T: Send + 'static>(
+    future: impl Future<Output = T> + Send + 'static,
+) -> Task<T> {
+    // Packing inside a compatability adapter is sufficient to prevent the
+    // Tokio runtime error that occurs without.
+    IoTaskPool::get().spawn(Compat::new(future))
+}
+
+/// Spawns a future on the `IoTaskPool`, in a context where the futures of
+/// every [`HttpTransport`] can run.
+#[cfg(target_family = "wasm")]
+pub fn spawn_io_task<T: 'static>(future: impl Future<Output = T> + 'static) -> Task<T> {
+    IoTaskPool::get().spawn(future)
+}
+
+impl Default for HttpRequestTransport {
+    fn default() -> Self {
+        Self::new(ReqwestTransport::default())
+    }
+}
+
+/// Sends requests over the network with a reqwest [`Client`].
+#[derive(Default, Clone)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    #[must_use]
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: Request) -> TransportFuture {
+        let client = self.client.clone();
+
+        Box::pin(async move {
+            let response = client.execute(request).await?;
+            let status_code = response.status();
+
+            Ok(HttpResponse::new(status_code, response.bytes().await?))
+        })
+    }
+}
+
+type InMemoryHandler = dyn Fn(&Request) -> Result<HttpResponse, HttpRequestError> + Send + Sync;
+
+/// Answers requests in memory with a handler function, without opening any
+/// sockets. Useful for tests and headless CI.
+#[derive(Clone)]
+pub struct InMemoryTransport {
+    handler: Arc<InMemoryHandler>,
+}
+
+impl InMemoryTransport {
+    #[must_use]
+    pub fn new(
+        handler: impl Fn(&Request) -> Result<HttpResponse, HttpRequestError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            handler: Arc::new(handler),
+        }
+    }
+}
+
+impl HttpTransport for InMemoryTransport {
+    fn send(&self, request: Request) -> TransportFuture {
+        let result = (self.handler)(&request);
+
+        Box::pin(async move { result })
+    }
+}
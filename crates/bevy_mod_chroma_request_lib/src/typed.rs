@@ -0,0 +1,172 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    ecs::system::System,
+    prelude::{Commands, Component, Entity, IntoSystem, Res, With, World},
+};
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    plugin::{HttpRequestClient, HttpResponseReceived},
+    HttpRequestError, HttpRequestHandle, HttpRequestOptions, HttpRequests, HttpResponse,
+};
+
+/// A handle to a request whose response body is decoded as JSON into `T`.
+///
+/// Use [`HttpRequests::poll_typed`] to receive the decoded response, which
+/// also disposes of the request. A handle that is dropped without being
+/// polled disposes of its request during the next
+/// [`HttpRequestSet::AfterGatherResponses`](crate::HttpRequestSet::AfterGatherResponses).
+pub struct TypedRequestHandle<T> {
+    handle: HttpRequestHandle,
+    /// Taken once the request has been disposed of, so that dropping the
+    /// handle doesn't dispose of it again
+    dropped_requests: Option<DroppedRequests>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Drop for TypedRequestHandle<T> {
+    fn drop(&mut self) {
+        if let Some(dropped_requests) = self.dropped_requests.take() {
+            dropped_requests.lock().unwrap().push(self.handle.entity);
+        }
+    }
+}
+
+/// Requests whose typed handles were dropped without being disposed of,
+/// waiting to be despawned
+pub(crate) type DroppedRequests = Arc<Mutex<Vec<Entity>>>;
+
+impl<T> Debug for TypedRequestHandle<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TypedRequestHandle")
+            .field(&self.handle)
+            .finish()
+    }
+}
+
+type Callback = Box<dyn FnOnce(&mut World, Result<HttpResponse, HttpRequestError>) + Send + Sync>;
+
+#[derive(Component)]
+pub(crate) struct HttpRequestCallback {
+    callback: Callback,
+}
+
+impl<'w, 's> HttpRequests<'w, 's> {
+    #[must_use]
+    pub fn request_typed<T: DeserializeOwned>(
+        &mut self,
+        request: RequestBuilder,
+    ) -> TypedRequestHandle<T> {
+        self.request_typed_with_options(request, HttpRequestOptions::default())
+    }
+
+    #[must_use]
+    pub fn request_typed_with_options<T: DeserializeOwned>(
+        &mut self,
+        request: RequestBuilder,
+        options: HttpRequestOptions,
+    ) -> TypedRequestHandle<T> {
+        TypedRequestHandle {
+            handle: self.request_with_options(request, options),
+            dropped_requests: Some(self.client.dropped_requests.clone()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the decoded response once it has arrived, taking the handle
+    /// out of `handle` and disposing of the request.
+    ///
+    /// Does nothing if `handle` is `None`, so a `Local<Option<_>>` or
+    /// component field can be polled every frame.
+    pub fn poll_typed<T: DeserializeOwned>(
+        &mut self,
+        handle: &mut Option<TypedRequestHandle<T>>,
+    ) -> Option<Result<T, HttpRequestError>> {
+        let result = self.get_response(&handle.as_ref()?.handle)?;
+        let result = decode(result.as_ref().map_err(Clone::clone));
+
+        self.dispose_typed(handle.take().expect("handle was checked to be Some above"));
+
+        Some(result)
+    }
+
+    /// Disposes of a typed request without waiting for its response. See
+    /// [`HttpRequests::dispose`].
+    pub fn dispose_typed<T>(&mut self, mut handle: TypedRequestHandle<T>) {
+        handle.dropped_requests = None;
+        self.commands.entity(handle.handle.entity).despawn();
+    }
+
+    /// Sends a request, then runs `callback` with the decoded response once
+    /// it arrives. The request is disposed of automatically.
+    ///
+    /// Callbacks run during [`HttpRequestSet::GatherResponses`](crate::HttpRequestSet::GatherResponses).
+    pub fn request_with_callback<T, M>(
+        &mut self,
+        request: RequestBuilder,
+        options: HttpRequestOptions,
+        callback: impl IntoSystem<Result<T, HttpRequestError>, (), M>,
+    ) where
+        T: DeserializeOwned + 'static,
+    {
+        let mut system = IntoSystem::into_system(callback);
+        let callback: Callback = Box::new(move |world, result| {
+            system.initialize(world);
+            system.run(decode(result.as_ref().map_err(Clone::clone)), world);
+            system.apply_deferred(world);
+        });
+
+        let handle = self.request_with_options(request, options);
+        self.commands
+            .entity(handle.entity)
+            .insert(HttpRequestCallback { callback });
+    }
+}
+
+fn decode<T: DeserializeOwned>(
+    result: Result<&HttpResponse, HttpRequestError>,
+) -> Result<T, HttpRequestError> {
+    Ok(result?.json()?)
+}
+
+pub(crate) fn system_dispose_dropped_requests(
+    mut commands: Commands,
+    client: Res<HttpRequestClient>,
+) {
+    for entity in client.dropped_requests.lock().unwrap().drain(..) {
+        // The request may already have been despawned, such as by
+        // `HttpRequests::dispose_by_id`
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.despawn();
+        }
+    }
+}
+
+pub(crate) fn system_run_callbacks(world: &mut World) {
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, (With<HttpResponseReceived>, With<HttpRequestCallback>)>()
+        .iter(world)
+        .collect();
+
+    for entity in entities {
+        let mut request = world.entity_mut(entity);
+
+        let callback = request
+            .take::<HttpRequestCallback>()
+            .expect("query only matches requests with a callback")
+            .callback;
+        let result = request
+            .take::<HttpResponseReceived>()
+            .expect("query only matches requests with a response")
+            .result;
+        request.despawn();
+
+        callback(world, result);
+    }
+}
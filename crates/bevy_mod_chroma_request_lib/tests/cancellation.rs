@@ -0,0 +1,29 @@
+mod common;
+
+use std::{sync::atomic::Ordering, time::Duration};
+
+use bevy_mod_chroma_request_lib::{HttpRequestOptions, HttpRequestPlugin};
+
+use common::{app, dispose, send, update_until, HungTransport};
+
+#[test]
+fn dispose_aborts_in_flight_request() {
+    let transport = HungTransport::default();
+    let dropped = transport.dropped.clone();
+    let mut app = app(HttpRequestPlugin::default(), transport);
+
+    let handle = send(
+        &mut app,
+        |client| client.get("http://chroma.invalid/"),
+        HttpRequestOptions::default(),
+    );
+
+    // Send the request, and give the task a chance to start
+    app.update();
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(dropped.load(Ordering::SeqCst), 0);
+
+    dispose(&mut app, handle);
+
+    update_until(&mut app, |_| dropped.load(Ordering::SeqCst) > 0);
+}
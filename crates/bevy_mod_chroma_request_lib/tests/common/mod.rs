@@ -0,0 +1,145 @@
+//! Fixtures shared by the integration tests. Each test file only uses some of
+//! them.
+#![allow(dead_code)]
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use bevy::{ecs::system::SystemState, prelude::*};
+use bevy_mod_chroma_request_lib::{
+    HttpRequestError, HttpRequestHandle, HttpRequestOptions, HttpRequestPlugin,
+    HttpRequestTransport, HttpRequests, HttpResponse, HttpTransport, InMemoryTransport,
+    TransportFuture,
+};
+use bytes::Bytes;
+use reqwest::{Client, Request, RequestBuilder, StatusCode};
+
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A transport that never answers, like a hung server. Records the path of
+/// every request it is asked to send, and counts how many of its futures have
+/// been dropped.
+#[derive(Clone, Default)]
+pub struct HungTransport {
+    pub sent: Arc<Mutex<Vec<String>>>,
+    pub dropped: Arc<AtomicUsize>,
+}
+
+struct DropCounter(Arc<AtomicUsize>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl HttpTransport for HungTransport {
+    fn send(&self, request: Request) -> TransportFuture {
+        self.sent
+            .lock()
+            .unwrap()
+            .push(request.url().path().to_owned());
+        let counter = DropCounter(self.dropped.clone());
+
+        Box::pin(async move {
+            let _counter = counter;
+            std::future::pending().await
+        })
+    }
+}
+
+/// A transport that answers every request with the same response.
+pub fn respond_with(status: StatusCode, body: impl Into<Bytes>) -> InMemoryTransport {
+    let body = body.into();
+
+    InMemoryTransport::new(move |_| Ok(HttpResponse::new(status, body.clone())))
+}
+
+pub fn app(plugin: HttpRequestPlugin, transport: impl HttpTransport) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, plugin))
+        .insert_resource(HttpRequestTransport::new(transport));
+    app
+}
+
+/// Queues the request built from the app's client, to be sent on the next
+/// update.
+pub fn send(
+    app: &mut App,
+    request: impl FnOnce(&Client) -> RequestBuilder,
+    options: HttpRequestOptions,
+) -> HttpRequestHandle {
+    let mut state = SystemState::<HttpRequests>::new(&mut app.world);
+    let mut requests = state.get_mut(&mut app.world);
+    let request = request(requests.client());
+    let handle = requests.request_with_options(request, options);
+    state.apply(&mut app.world);
+
+    handle
+}
+
+pub fn dispose(app: &mut App, handle: HttpRequestHandle) {
+    let mut state = SystemState::<HttpRequests>::new(&mut app.world);
+    state.get_mut(&mut app.world).dispose(handle);
+    state.apply(&mut app.world);
+}
+
+/// Updates the app until the request has a response or `wait` has passed.
+pub fn wait_for_response(
+    app: &mut App,
+    handle: &HttpRequestHandle,
+    wait: Duration,
+) -> Option<Result<HttpResponse, HttpRequestError>> {
+    let mut state = SystemState::<HttpRequests>::new(&mut app.world);
+
+    let start = Instant::now();
+    while start.elapsed() < wait {
+        app.update();
+
+        let requests = state.get_mut(&mut app.world);
+        if let Some(result) = requests.get_response(handle) {
+            return Some(result.clone());
+        }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    None
+}
+
+/// Updates the app until the request has a response, and returns it.
+pub fn response(
+    app: &mut App,
+    handle: &HttpRequestHandle,
+) -> Result<HttpResponse, HttpRequestError> {
+    wait_for_response(app, handle, TIMEOUT).expect("timed out")
+}
+
+/// Sends the request built from the app's client, and updates the app until it
+/// has a response.
+pub fn fetch(
+    app: &mut App,
+    request: impl FnOnce(&Client) -> RequestBuilder,
+    options: HttpRequestOptions,
+) -> Result<HttpResponse, HttpRequestError> {
+    let handle = send(app, request, options);
+
+    response(app, &handle)
+}
+
+/// Updates the app until `condition` holds.
+pub fn update_until(app: &mut App, condition: impl Fn(&mut App) -> bool) {
+    let start = Instant::now();
+
+    while !condition(app) {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+
+        app.update();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
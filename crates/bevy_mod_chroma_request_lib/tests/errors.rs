@@ -0,0 +1,42 @@
+mod common;
+
+use std::error::Error;
+
+use bevy::prelude::*;
+use bevy_mod_chroma_request_lib::{HttpRequestError, HttpRequestOptions, HttpRequestPlugin};
+use reqwest::StatusCode;
+
+use common::{fetch, respond_with};
+
+fn busy_app() -> App {
+    common::app(
+        HttpRequestPlugin::default(),
+        respond_with(StatusCode::SERVICE_UNAVAILABLE, "busy"),
+    )
+}
+
+/// Sends a request and waits for it to fail.
+fn send_and_fail(app: &mut App, url: &str) -> HttpRequestError {
+    fetch(app, |client| client.get(url), HttpRequestOptions::default()).unwrap_err()
+}
+
+#[test]
+fn non_success_status_is_an_error() {
+    let mut app = busy_app();
+
+    let error = send_and_fail(&mut app, "http://chroma.invalid/");
+    assert!(matches!(
+        &error,
+        HttpRequestError::Status(StatusCode::SERVICE_UNAVAILABLE, body) if body == "busy"
+    ));
+    assert!(error.source().is_none());
+}
+
+#[test]
+fn invalid_request_is_a_builder_error() {
+    let mut app = busy_app();
+
+    let error = send_and_fail(&mut app, "not a url");
+    assert!(matches!(error, HttpRequestError::Builder(_)));
+    assert!(error.source().is_some());
+}
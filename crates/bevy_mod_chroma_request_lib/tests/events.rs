@@ -0,0 +1,56 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_mod_chroma_request_lib::{
+    HttpRequestId, HttpRequestOptions, HttpRequestPlugin, HttpRequests, HttpResponseEvent,
+};
+use reqwest::StatusCode;
+
+use common::{app, respond_with, send, update_until};
+
+#[derive(Resource, Default)]
+struct Received(Vec<(HttpRequestId, StatusCode)>);
+
+fn read_responses(
+    mut events: EventReader<HttpResponseEvent>,
+    mut requests: HttpRequests,
+    mut received: ResMut<Received>,
+) {
+    for event in events.iter() {
+        let response = event.result.as_ref().unwrap();
+        received.0.push((event.handle_id, response.status_code()));
+        requests.dispose_by_id(event.handle_id);
+    }
+}
+
+#[test]
+fn responses_are_sent_as_events() {
+    let mut app = app(
+        HttpRequestPlugin {
+            emit_events: true,
+            ..default()
+        },
+        respond_with(StatusCode::ACCEPTED, ""),
+    );
+    app.init_resource::<Received>()
+        .add_systems(Update, read_responses);
+
+    let handle = send(
+        &mut app,
+        |client| client.get("http://chroma.invalid/"),
+        HttpRequestOptions::default(),
+    );
+
+    update_until(&mut app, |app| {
+        !app.world.resource::<Received>().0.is_empty()
+    });
+
+    assert_eq!(
+        app.world.resource::<Received>().0,
+        [(handle.id(), StatusCode::ACCEPTED)]
+    );
+
+    // The request was disposed of by id, so nothing is left behind
+    let mut query = app.world.query::<Entity>();
+    assert_eq!(query.iter(&app.world).count(), 0);
+}
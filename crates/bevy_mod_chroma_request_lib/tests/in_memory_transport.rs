@@ -0,0 +1,80 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_mod_chroma_request_lib::{
+    ExecuteHttpRequests, HttpRequestHandle, HttpRequestPlugin, HttpRequestSet, HttpRequests,
+    HttpResponse, InMemoryTransport,
+};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use common::{app, update_until};
+
+#[derive(Resource, Default)]
+struct Received(Option<(StatusCode, Value)>);
+
+fn send_request(mut commands: Commands, mut requests: HttpRequests) {
+    let handle = requests.request(
+        requests
+            .client()
+            .put("http://chroma.invalid/heartbeat")
+            .json(&json!({ "ping": true })),
+    );
+
+    commands.insert_resource(InFlight(Some(handle)));
+}
+
+#[derive(Resource)]
+struct InFlight(Option<HttpRequestHandle>);
+
+fn gather_response(
+    mut requests: HttpRequests,
+    mut in_flight: ResMut<InFlight>,
+    mut received: ResMut<Received>,
+) {
+    let Some(handle) = in_flight.0.as_ref() else {
+        return;
+    };
+
+    if let Some(result) = requests.get_response(handle) {
+        let response = result.as_ref().unwrap();
+        received.0 = Some((response.status_code(), response.json().unwrap()));
+
+        let handle = in_flight.0.take().unwrap();
+        requests.dispose(handle);
+    }
+}
+
+#[test]
+fn requests_are_answered_by_in_memory_transport() {
+    let transport = InMemoryTransport::new(|request| {
+        assert_eq!(request.method(), Method::PUT);
+        assert_eq!(request.url().path(), "/heartbeat");
+
+        let body: Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(body, json!({ "ping": true }));
+
+        Ok(HttpResponse::new(
+            StatusCode::OK,
+            json!({ "tick": 1 }).to_string().into(),
+        ))
+    });
+
+    let mut app = app(HttpRequestPlugin::default(), transport);
+    app.init_resource::<Received>()
+        .add_systems(Startup, send_request)
+        .add_systems(
+            ExecuteHttpRequests,
+            gather_response
+                .in_set(HttpRequestSet::AfterGatherResponses)
+                .run_if(resource_exists::<InFlight>()),
+        );
+
+    update_until(&mut app, |app| app.world.resource::<Received>().0.is_some());
+
+    assert_eq!(
+        app.world.resource::<Received>().0,
+        Some((StatusCode::OK, json!({ "tick": 1 })))
+    );
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_mod_chroma_request_lib::{
+    HttpRequestHandle, HttpRequestOptions, HttpRequestPlugin, HttpRequestPriority,
+};
+
+use common::{app, dispose, send, HungTransport};
+
+fn request(app: &mut App, path: &str, priority: HttpRequestPriority) -> HttpRequestHandle {
+    send(
+        app,
+        |client| client.get(format!("http://chroma.invalid/{path}")),
+        HttpRequestOptions::default().with_priority(priority),
+    )
+}
+
+#[test]
+fn queued_requests_are_sent_by_priority_then_in_order() {
+    let transport = HungTransport::default();
+    let sent = transport.sent.clone();
+    let mut app = app(
+        HttpRequestPlugin {
+            max_in_flight: Some(2),
+            ..default()
+        },
+        transport,
+    );
+
+    let first = request(&mut app, "first", HttpRequestPriority::Normal);
+    request(&mut app, "second", HttpRequestPriority::Normal);
+    request(&mut app, "low", HttpRequestPriority::Low);
+    request(&mut app, "third", HttpRequestPriority::Normal);
+    request(&mut app, "heartbeat", HttpRequestPriority::High);
+
+    app.update();
+    std::thread::sleep(Duration::from_millis(5));
+    app.update();
+
+    // High priority requests skip the limit
+    assert_eq!(*sent.lock().unwrap(), ["/heartbeat", "/first", "/second"]);
+
+    dispose(&mut app, first);
+
+    app.update();
+    app.update();
+
+    assert_eq!(
+        *sent.lock().unwrap(),
+        ["/heartbeat", "/first", "/second", "/third"]
+    );
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_mod_chroma_request_lib::{
+    HttpRequestError, HttpRequestOptions, HttpRequestPlugin, HttpResponse, HttpRetryPolicy,
+    InMemoryTransport,
+};
+use reqwest::StatusCode;
+
+use common::fetch;
+
+/// An app whose transport answers with 503 `failures` times, then 200,
+/// alongside a count of how many requests it has answered.
+fn flaky_app(failures: u32) -> (App, Arc<AtomicU32>) {
+    let attempts = Arc::new(AtomicU32::new(0));
+
+    let transport = InMemoryTransport::new({
+        let attempts = attempts.clone();
+        move |_| {
+            let status = if attempts.fetch_add(1, Ordering::SeqCst) < failures {
+                StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                StatusCode::OK
+            };
+
+            Ok(HttpResponse::new(status, "{}".into()))
+        }
+    });
+
+    let app = common::app(
+        HttpRequestPlugin {
+            retry_policy: HttpRetryPolicy {
+                initial_backoff: Duration::from_millis(10),
+                ..default()
+            },
+            ..default()
+        },
+        transport,
+    );
+
+    (app, attempts)
+}
+
+/// Sends a request and waits for its response.
+fn send_put(app: &mut App, options: HttpRequestOptions) -> Result<HttpResponse, HttpRequestError> {
+    fetch(app, |client| client.put("http://chroma.invalid/"), options)
+}
+
+#[test]
+fn idempotent_request_is_retried_until_it_succeeds() {
+    let (mut app, attempts) = flaky_app(2);
+
+    let result = send_put(&mut app, HttpRequestOptions::default().idempotent());
+    assert_eq!(result.unwrap().status_code(), StatusCode::OK);
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn request_is_not_retried_unless_idempotent() {
+    let (mut app, attempts) = flaky_app(1);
+
+    let result = send_put(&mut app, HttpRequestOptions::default());
+    assert!(matches!(
+        result,
+        Err(HttpRequestError::Status(StatusCode::SERVICE_UNAVAILABLE, _))
+    ));
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn error_reports_attempts_once_retries_are_exhausted() {
+    let (mut app, attempts) = flaky_app(u32::MAX);
+
+    let error = send_put(
+        &mut app,
+        HttpRequestOptions::default().with_retry_policy(HttpRetryPolicy {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(1),
+            ..default()
+        }),
+    )
+    .unwrap_err();
+
+    let HttpRequestError::RetriesExhausted {
+        attempts: reported,
+        last_error,
+    } = &error
+    else {
+        panic!("unexpected error: {error}");
+    };
+    assert_eq!(*reported, 4);
+    assert!(matches!(
+        **last_error,
+        HttpRequestError::Status(StatusCode::SERVICE_UNAVAILABLE, _)
+    ));
+    assert!(error.source().is_some());
+    assert_eq!(attempts.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn backoff_grows_up_to_its_maximum() {
+    let policy = HttpRetryPolicy {
+        initial_backoff: Duration::from_millis(100),
+        backoff_multiplier: 2.0,
+        max_backoff: Duration::from_millis(300),
+        ..default()
+    };
+
+    assert_eq!(policy.backoff(1).as_millis(), 100);
+    assert_eq!(policy.backoff(2).as_millis(), 200);
+    assert_eq!(policy.backoff(3).as_millis(), 300);
+    assert_eq!(policy.backoff(10).as_millis(), 300);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_mod_chroma_request_lib::{
+    read_recording, HttpRequestError, HttpRequestOptions, HttpRequestPlugin, RecordedResponse,
+    RecordingTransport,
+};
+
+use common::{app, send, update_until, wait_for_response, HungTransport, TIMEOUT};
+
+/// Sends a request to a hung server and updates the app until it has a
+/// response or `wait` has passed, returning whether it timed out and how long
+/// that took.
+fn send_and_wait(app: &mut App, options: HttpRequestOptions, wait: Duration) -> (bool, Duration) {
+    let handle = send(app, |client| client.get("http://chroma.invalid/"), options);
+
+    let start = Instant::now();
+    let result = wait_for_response(app, &handle, wait);
+
+    (
+        matches!(result, Some(Err(HttpRequestError::Timeout))),
+        start.elapsed(),
+    )
+}
+
+#[test]
+fn request_times_out_after_its_own_timeout() {
+    let mut app = app(
+        HttpRequestPlugin {
+            default_timeout: None,
+            ..default()
+        },
+        HungTransport::default(),
+    );
+
+    let (timed_out, elapsed) = send_and_wait(
+        &mut app,
+        HttpRequestOptions::default().with_timeout(Duration::from_millis(50)),
+        TIMEOUT,
+    );
+
+    assert!(timed_out);
+    assert!(elapsed >= Duration::from_millis(50));
+}
+
+#[test]
+fn request_times_out_after_default_timeout() {
+    let mut app = app(
+        HttpRequestPlugin {
+            default_timeout: Some(Duration::from_millis(50)),
+            ..default()
+        },
+        HungTransport::default(),
+    );
+
+    let (timed_out, _) = send_and_wait(&mut app, HttpRequestOptions::default(), TIMEOUT);
+    assert!(timed_out);
+
+    let (timed_out, _) = send_and_wait(
+        &mut app,
+        HttpRequestOptions::default().without_timeout(),
+        Duration::from_millis(250),
+    );
+    assert!(!timed_out);
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn timed_out_request_is_recorded() {
+    let recording = SharedBuffer::default();
+    let mut app = app(
+        HttpRequestPlugin::default(),
+        RecordingTransport::new(HungTransport::default(), recording.clone()),
+    );
+
+    let (timed_out, _) = send_and_wait(
+        &mut app,
+        HttpRequestOptions::default().with_timeout(Duration::from_millis(50)),
+        TIMEOUT,
+    );
+    assert!(timed_out);
+
+    // The aborted request is dropped on the task pool after it times out
+    update_until(&mut app, |_| !recording.0.lock().unwrap().is_empty());
+
+    let exchanges = read_recording(recording.0.lock().unwrap().as_slice()).unwrap();
+    assert_eq!(exchanges.len(), 1);
+    assert_eq!(exchanges[0].method, "GET");
+    assert_eq!(
+        exchanges[0].response,
+        RecordedResponse::Failed {
+            error: "cancelled".to_owned()
+        }
+    );
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use bevy::{
+    prelude::*,
+    utils::{
+        tracing::{
+            field::{Field, Visit},
+            span::{Attributes, Id, Record},
+            subscriber, Event, Metadata, Subscriber,
+        },
+        HashMap,
+    },
+};
+use bevy_mod_chroma_request_lib::{
+    HttpRequestOptions, HttpRequestPlugin, HttpResponse, InMemoryTransport,
+};
+use reqwest::StatusCode;
+
+use common::{app, response, send};
+
+/// A span and the fields recorded on it so far.
+#[derive(Debug, Clone, Default)]
+struct RecordedSpan {
+    name: &'static str,
+    parent: Option<u64>,
+    fields: HashMap<&'static str, String>,
+}
+
+impl Visit for RecordedSpan {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.fields.insert(field.name(), format!("{value:?}"));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.insert(field.name(), value.to_string());
+    }
+}
+
+/// Records every span that is created, and the fields recorded on them.
+#[derive(Clone, Default)]
+struct SpanRecorder {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<HashMap<u64, RecordedSpan>>>,
+}
+
+impl SpanRecorder {
+    fn find(&self, predicate: impl Fn(&RecordedSpan) -> bool) -> Option<(u64, RecordedSpan)> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, span)| predicate(span))
+            .map(|(id, span)| (*id, span.clone()))
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let mut span = RecordedSpan {
+            name: attributes.metadata().name(),
+            parent: attributes.parent().map(Id::into_u64),
+            ..default()
+        };
+        attributes.record(&mut span);
+
+        self.spans.lock().unwrap().insert(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            values.record(span);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn spans_cover_request_lifecycle() {
+    // Systems run on other threads, so the subscriber must be global rather
+    // than set for this thread only
+    let recorder = SpanRecorder::default();
+    subscriber::set_global_default(recorder.clone()).unwrap();
+
+    let transport = InMemoryTransport::new(|request| {
+        let status = if request.url().path() == "/fail" {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        };
+
+        Ok(HttpResponse::new(status, "".into()))
+    });
+
+    let mut app = app(HttpRequestPlugin::default(), transport);
+
+    let handle = send(
+        &mut app,
+        |client| client.put("http://chroma.invalid/mouse"),
+        HttpRequestOptions::default(),
+    );
+    let failing_handle = send(
+        &mut app,
+        |client| client.put("http://chroma.invalid/fail"),
+        HttpRequestOptions::default(),
+    );
+
+    let _ = response(&mut app, &handle);
+    let _ = response(&mut app, &failing_handle);
+
+    let (request_id, request) = recorder
+        .find(|span| {
+            span.name == "http_request"
+                && span.fields.get("url").map(String::as_str) == Some("http://chroma.invalid/mouse")
+        })
+        .unwrap();
+    assert_eq!(request.fields["method"], "PUT");
+    assert_eq!(request.fields["status"], "200");
+    assert!(!request.fields.contains_key("error"));
+
+    // Failures keep the status code in `status`, and describe the error
+    // separately
+    let (_, failed) = recorder
+        .find(|span| {
+            span.name == "http_request"
+                && span.fields.get("url").map(String::as_str) == Some("http://chroma.invalid/fail")
+        })
+        .unwrap();
+    assert_eq!(failed.fields["status"], "500");
+    assert!(failed.fields["error"].contains("500"));
+
+    for name in ["http_request_send", "http_request_response"] {
+        assert!(recorder
+            .find(|span| span.name == name && span.parent == Some(request_id))
+            .is_some());
+    }
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use bevy::{ecs::system::SystemState, prelude::*};
+use bevy_mod_chroma_request_lib::{
+    HttpRequestError, HttpRequestOptions, HttpRequestPlugin, HttpRequests, HttpResponseEvent,
+    TypedRequestHandle,
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+
+use common::{respond_with, update_until};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Tick {
+    tick: u32,
+}
+
+fn tick_app(plugin: HttpRequestPlugin) -> App {
+    common::app(
+        plugin,
+        respond_with(StatusCode::OK, json!({ "tick": 7 }).to_string()),
+    )
+}
+
+#[derive(Resource, Default)]
+struct Polled(Option<Result<Tick, HttpRequestError>>);
+
+fn poll_tick(
+    mut handle: Local<Option<TypedRequestHandle<Tick>>>,
+    mut sent: Local<bool>,
+    mut requests: HttpRequests,
+    mut polled: ResMut<Polled>,
+) {
+    if !*sent {
+        *handle = Some(requests.request_typed(requests.client().put("http://chroma.invalid/")));
+        *sent = true;
+    }
+
+    if let Some(result) = requests.poll_typed(&mut handle) {
+        polled.0 = Some(result);
+    }
+}
+
+#[test]
+fn typed_handle_decodes_response() {
+    let mut app = tick_app(HttpRequestPlugin::default());
+    app.init_resource::<Polled>().add_systems(Update, poll_tick);
+
+    update_until(&mut app, |app| app.world.resource::<Polled>().0.is_some());
+
+    assert_eq!(
+        app.world
+            .resource::<Polled>()
+            .0
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap(),
+        &Tick { tick: 7 }
+    );
+}
+
+#[test]
+fn callback_runs_with_decoded_response() {
+    let mut app = tick_app(HttpRequestPlugin::default());
+    app.init_resource::<Polled>();
+
+    let mut state = SystemState::<HttpRequests>::new(&mut app.world);
+    let mut requests = state.get_mut(&mut app.world);
+    let request = requests.client().put("http://chroma.invalid/");
+    requests.request_with_callback(
+        request,
+        HttpRequestOptions::default(),
+        |In(result): In<Result<Tick, HttpRequestError>>, mut polled: ResMut<Polled>| {
+            polled.0 = Some(result);
+        },
+    );
+    state.apply(&mut app.world);
+
+    update_until(&mut app, |app| app.world.resource::<Polled>().0.is_some());
+
+    assert_eq!(
+        app.world
+            .resource::<Polled>()
+            .0
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap(),
+        &Tick { tick: 7 }
+    );
+
+    // The request is disposed of once its callback has run
+    let mut query = app.world.query::<Entity>();
+    assert_eq!(query.iter(&app.world).count(), 0);
+}
+
+#[test]
+fn dropped_handle_disposes_of_request() {
+    let mut app = tick_app(HttpRequestPlugin::default());
+
+    let mut state = SystemState::<HttpRequests>::new(&mut app.world);
+    let mut requests = state.get_mut(&mut app.world);
+    let request = requests.client().put("http://chroma.invalid/");
+    let handle: TypedRequestHandle<Tick> = requests.request_typed(request);
+    state.apply(&mut app.world);
+
+    let mut query = app.world.query::<Entity>();
+    assert_eq!(query.iter(&app.world).count(), 1);
+
+    drop(handle);
+    app.update();
+
+    assert_eq!(query.iter(&app.world).count(), 0);
+}
+
+#[test]
+fn dropped_handle_of_request_disposed_by_id_is_ignored() {
+    let mut app = tick_app(HttpRequestPlugin {
+        emit_events: true,
+        ..default()
+    });
+
+    let mut state = SystemState::<HttpRequests>::new(&mut app.world);
+    let mut requests = state.get_mut(&mut app.world);
+    let request = requests.client().put("http://chroma.invalid/");
+    let handle: TypedRequestHandle<Tick> = requests.request_typed(request);
+    state.apply(&mut app.world);
+
+    update_until(&mut app, |app| {
+        !app.world.resource::<Events<HttpResponseEvent>>().is_empty()
+    });
+
+    // Dispose of the request through its event, then drop its handle in the
+    // same frame
+    let id = app
+        .world
+        .resource::<Events<HttpResponseEvent>>()
+        .iter_current_update_events()
+        .next()
+        .unwrap()
+        .handle_id;
+    let mut requests = state.get_mut(&mut app.world);
+    requests.dispose_by_id(id);
+    drop(handle);
+    state.apply(&mut app.world);
+
+    app.update();
+
+    let mut query = app.world.query::<Entity>();
+    assert_eq!(query.iter(&app.world).count(), 0);
+}
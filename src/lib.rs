@@ -4,10 +4,34 @@ pub use bevy_mod_chroma_api::{
 
 pub use bevy_mod_chroma_api::{Chroma, ChromaPlugin, EffectHandle};
 
+pub use bevy_mod_chroma_api::{ChromaCalibration, DeviceCalibration};
+
+pub use bevy_mod_chroma_api::{
+    ChromaDeviceState, ChromaDevices, DeviceStatus, MissingDevicePolicy,
+};
+
+pub use bevy_mod_chroma_api::{ChromaBrightness, DimOnPause};
+pub use bevy_mod_chroma_api::{ChromaHealth, ChromaHealthChanged, ChromaHeartbeatSettings};
+
+pub use bevy_mod_chroma_api::ChromaDiagnosticsPlugin;
+
 pub use bevy_mod_chroma_api::api::Effect;
 
+pub use bevy_mod_chroma_api::AllDevicesEffect;
+
 pub use bevy_mod_chroma_api::api::{
     ChromaLinkEffect, HeadsetEffect, KeyboardEffect, KeypadEffect, MouseEffect, MousepadEffect,
 };
 
-pub use bevy_mod_chroma_api::{BGRColor, KeyColor};
+pub use bevy_mod_chroma_api::{BGRColor, ColorGrid, Gradient, GradientColor, KeyColor};
+
+pub use bevy_mod_chroma_api::bgr_color;
+
+pub use bevy_mod_chroma_api::ChromaPreviewTransport;
+
+pub use bevy_mod_chroma_api::{ChromaReplay, ChromaReplayPlugin};
+
+pub use bevy_mod_chroma_request_lib::HttpRequestTransport;
+pub use bevy_mod_chroma_request_lib::{
+    read_recording, RecordedExchange, RecordedResponse, RecordingTransport,
+};